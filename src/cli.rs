@@ -1,13 +1,15 @@
 use std::env;
 use std::ffi::OsString;
 use std::path::PathBuf;
-use clap::Args;
+use clap::{Args, Subcommand, ValueEnum};
+use semver::Version;
+use sheepit::{BumpMode, InitOptions, UndoOptions, UpdateOptions};
 
 #[derive(Args, Debug)]
 #[command(about = "Performs a major version bump")]
 pub struct MajorBumpArgs {
-    #[arg(short = 'd', long)]
-    pub dry_run: bool,
+    #[command(flatten)]
+    pub update: UpdateArgs,
     #[
     arg(value_name = "repo",
     default_value=".",
@@ -19,8 +21,8 @@ pub struct MajorBumpArgs {
 #[derive(Args, Debug)]
 #[command(about = "Performs a minor version bump")]
 pub struct MinorBumpArgs {
-    #[arg(short = 'd', long)]
-    pub dry_run: bool,
+    #[command(flatten)]
+    pub update: UpdateArgs,
     #[
     arg(value_name = "repo",
     default_value=get_default_path(),
@@ -32,8 +34,234 @@ pub struct MinorBumpArgs {
 #[derive(Args, Debug)]
 #[command(about = "Performs a patch version bump")]
 pub struct PatchBumpArgs {
+    #[command(flatten)]
+    pub update: UpdateArgs,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Releases the config's packages")]
+pub struct ReleaseArgs {
+    #[command(flatten)]
+    pub update: UpdateArgs,
+    /// The version bump applied to each released package
+    #[arg(short = 'b', long, value_enum, default_value_t = BumpArg::Patch)]
+    pub bump: BumpArg,
+    /// Only releases packages with changes since their latest tag
+    #[arg(long)]
+    pub changed: bool,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Lists the config's packages and whether they changed since their latest tag")]
+pub struct ChangedArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Undoes the branches, commits and tags created by the last run")]
+pub struct RollbackArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Also deletes refs which were pushed to the remote
+    #[arg(long)]
+    pub remote: bool,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Retracts a release by deleting its tag")]
+pub struct UndoArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// The version whose release is undone
+    #[arg(value_name = "version")]
+    pub version: Version,
+    /// Also deletes the release branch
+    #[arg(long)]
+    pub branch: bool,
+    /// Undoes a release of a single package from the config's packages
+    #[arg(short = 'p', long, value_name = "name")]
+    pub package: Option<String>,
+    /// Also reverts the release commit on the default branch
+    #[arg(long)]
+    pub revert: bool,
+    /// Undoes the release without asking first
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+impl UndoArgs {
+    pub fn options(&self) -> UndoOptions {
+        UndoOptions {
+            config_path: self.config.config_path.clone(),
+            delete_branch: self.branch,
+            package: self.package.clone(),
+            revert_commit: self.revert,
+            yes: self.yes,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Validates the config and checks it against the repository")]
+pub struct CheckArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Creates a config from the repository's manifests, tags and default branch")]
+pub struct InitArgs {
+    /// Replaces an existing config
+    #[arg(long)]
+    pub force: bool,
+    /// Writes the detected config without asking first
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+impl InitArgs {
+    pub fn options(&self) -> InitOptions {
+        InitOptions {
+            force: self.force,
+            yes: self.yes,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Prints the merged config and where each value came from")]
+pub struct ConfigShowArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    #[
+    arg(value_name = "repo",
+    default_value=get_default_path(),
+    value_hint = clap::ValueHint::DirPath)
+    ]
+    pub repo_path: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    Show(ConfigShowArgs),
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum BumpArg {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl BumpArg {
+    pub fn mode(&self) -> BumpMode {
+        match self {
+            BumpArg::Major => BumpMode::Major,
+            BumpArg::Minor => BumpMode::Minor,
+            BumpArg::Patch => BumpMode::Patch,
+        }
+    }
+}
+
+/// Options shared by every command which reads the config.
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// A config file used in place of the one found in the repository
+    #[arg(short = 'c', long = "config", value_name = "path", value_hint = clap::ValueHint::FilePath)]
+    pub config_path: Option<PathBuf>,
+}
+
+/// Options shared by every command which updates the project.
+#[derive(Args, Debug)]
+pub struct UpdateArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
     #[arg(short = 'd', long)]
     pub dry_run: bool,
+    /// Releases the configured initial version if no previous version exists
+    #[arg(long)]
+    pub initial: bool,
+    /// The number of subprojects released at the same time
+    #[arg(short = 'j', long, value_name = "count", default_value_t = 1)]
+    pub jobs: usize,
+    /// How many levels of nested subprojects are released
+    #[arg(long, value_name = "depth", conflicts_with = "no_subprojects")]
+    pub max_depth: Option<usize>,
+    /// Releases the project without its subprojects
+    #[arg(long)]
+    pub no_subprojects: bool,
+    /// Releases a single package from the config's packages
+    #[arg(short = 'p', long, value_name = "name")]
+    pub package: Option<String>,
+    /// Resumes a release which failed while releasing packages or subprojects
+    #[arg(long)]
+    pub resume: bool,
+}
+
+impl UpdateArgs {
+    pub fn options(&self) -> UpdateOptions {
+        UpdateOptions {
+            config_path: self.config.config_path.clone(),
+            dry_run: self.dry_run,
+            jobs: self.jobs,
+            max_depth: if self.no_subprojects { Some(0) } else { self.max_depth },
+            package: self.package.clone(),
+            resume: self.resume,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Lists tags and whether they are used to find the current version")]
+pub struct TagsArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Lists tags for a single package from the config's packages
+    #[arg(short = 'p', long, value_name = "name")]
+    pub package: Option<String>,
     #[
     arg(value_name = "repo",
     default_value=get_default_path(),
@@ -46,4 +274,4 @@ fn get_default_path() -> OsString {
     env::current_dir()
         .expect("unable to get working directory")
         .into_os_string()
-}
\ No newline at end of file
+}
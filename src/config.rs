@@ -1,12 +1,29 @@
+use crate::project::operation::BumpMode;
 use crate::SheepError;
+use semver::Version;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::token;
+use crate::version::tag_match::{TagMatcher, REGEX_PREFIX};
 
+mod extends;
 mod finder;
+mod layers;
+mod manifest;
 mod opener;
+mod scaffold;
 
-#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub use layers::{ConfigLayers, ConfigSource};
+pub use manifest::Manifest;
+pub use opener::UnknownKeys;
+pub use scaffold::ConfigScaffold;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct Config {
+    #[serde(default)]
+    pub packages: Vec<PackageConfig>,
     #[serde(default)]
     pub repository: RepoConfig,
     #[serde(default)]
@@ -18,15 +35,155 @@ pub struct Config {
 }
 
 impl Config {
-    pub fn open<P: AsRef<Path>>(repo_path: P) -> Result<Config, SheepError> {
-        opener::open_config(repo_path)
+    /// Opens the repository's config, layered over the user's config and overridden by
+    /// environment variables. `config_path` replaces the config found in the repository, which
+    /// may be missing, in which case defaults are used. Unknown keys are reported as warnings.
+    pub fn open<P: AsRef<Path>>(repo_path: P,
+                                config_path: Option<&Path>) -> Result<Config, SheepError> {
+        let layers = Config::layers(&repo_path, config_path, UnknownKeys::Warn)?;
+        Ok(layers.config()?.with_subproject_paths_from(repo_path))
+    }
+
+    /// Opens the repository's config like [`Config::open`], but fails if the repository has no
+    /// config or any layer contains unknown keys.
+    pub fn open_strict<P: AsRef<Path>>(repo_path: P,
+                                       config_path: Option<&Path>) -> Result<Config, SheepError> {
+        let layers = Config::layers(&repo_path, config_path, UnknownKeys::Deny)?;
+        Ok(layers.config()?.with_subproject_paths_from(repo_path))
+    }
+
+    /// The repository's config file, if it has one.
+    pub fn find<P: AsRef<Path>>(repo_path: P) -> Result<Option<PathBuf>, SheepError> {
+        opener::find_repo_config(repo_path)
+    }
+
+    /// Opens each layer of the repository's config, which can describe where values came from.
+    pub fn layers<P: AsRef<Path>>(repo_path: P,
+                                  config_path: Option<&Path>,
+                                  unknown_keys: UnknownKeys) -> Result<ConfigLayers, SheepError> {
+        opener::open_config_layers(repo_path, config_path, unknown_keys)
+    }
+
+    /// Resolves local subproject paths against the repository the config belongs to, so they
+    /// still point at the right place when the project itself is released from a copy.
+    fn with_subproject_paths_from<P: AsRef<Path>>(self, repo_path: P) -> Config {
+        let subprojects = self
+            .subprojects
+            .into_iter()
+            .map(|subproject| SubprojectConfig {
+                path: subproject
+                    .path
+                    .map(|path| repo_path.as_ref().join(path).to_string_lossy().to_string()),
+                ..subproject
+            })
+            .collect();
+        Config {
+            subprojects,
+            ..self
+        }
+    }
+
+    pub fn package(&self, name: &str) -> Result<&PackageConfig, SheepError> {
+        self.packages
+            .iter()
+            .find(|package| package.name == name)
+            .ok_or_else(|| SheepError::config(&format!("no package named {name} in config")))
+    }
+
+    /// Describes each tag or branch pattern which is missing the `{version}` token. Tag patterns
+    /// always need it to find versions, while branch patterns only need it when branches are
+    /// created.
+    pub fn pattern_problems(&self) -> Vec<String> {
+        let repository = &self.repository;
+        let mut patterns = vec![("repository.tag_pattern".to_string(), &repository.tag_pattern)];
+        if repository.enable_branch {
+            patterns.push(("repository.branch_pattern".to_string(), &repository.branch_pattern));
+        }
+        let mut problems = vec![];
+        for legacy_pattern in &repository.legacy_tag_patterns {
+            let name = "repository.legacy_tag_patterns".to_string();
+            if legacy_pattern.starts_with(REGEX_PREFIX) {
+                problems.extend(regex_problem(&name, legacy_pattern));
+            } else {
+                patterns.push((name, legacy_pattern));
+            }
+        }
+        for package in &self.packages {
+            let name = &package.name;
+            if let Some(tag_pattern) = &package.tag_pattern {
+                patterns.push((format!("package {name} tag_pattern"), tag_pattern));
+            }
+            if let (true, Some(branch_pattern)) = (repository.enable_branch, &package.branch_pattern) {
+                patterns.push((format!("package {name} branch_pattern"), branch_pattern));
+            }
+            for legacy_pattern in &package.legacy_tag_patterns {
+                let name = format!("package {name} legacy_tag_patterns");
+                if legacy_pattern.starts_with(REGEX_PREFIX) {
+                    problems.extend(regex_problem(&name, legacy_pattern));
+                } else {
+                    patterns.push((name, legacy_pattern));
+                }
+            }
+        }
+        let token_problems = patterns
+            .into_iter()
+            .filter(|(_, pattern)| !pattern.contains(token::VERSION))
+            .map(|(name, pattern)| format!("{name} '{pattern}' must contain {}", token::VERSION));
+        token_problems.chain(problems).collect()
+    }
+
+    /// Creates the configuration used to release a single package. Repository settings are
+    /// inherited, while tag patterns, scripts and transforms come from the package. Transform
+    /// paths are resolved relative to the package's path.
+    pub fn for_package(&self, package: &PackageConfig) -> Config {
+        let repository = RepoConfig {
+            branch_pattern: package
+                .branch_pattern
+                .clone()
+                .unwrap_or_else(|| self.repository.branch_pattern.clone()),
+            commit_message: package
+                .commit_message
+                .clone()
+                .unwrap_or_else(|| self.repository.commit_message.clone()),
+            legacy_tag_patterns: package.legacy_tag_patterns.clone(),
+            tag_pattern: package.tag_pattern(),
+            ..self.repository.clone()
+        };
+        let transforms = package
+            .transforms
+            .iter()
+            .map(|transform| TransformConfig {
+                path: package.relative_path(&transform.path),
+                ..transform.clone()
+            })
+            .collect();
+        Config {
+            packages: vec![],
+            repository,
+            scripts: package.scripts.clone(),
+            subprojects: vec![],
+            transforms,
+        }
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// Regex legacy patterns don't contain `{version}`, so they're checked by compiling them instead.
+fn regex_problem(name: &str, pattern: &str) -> Option<String> {
+    TagMatcher::new(pattern)
+        .err()
+        .map(|error| format!("{name} '{pattern}' is not a valid regex: {}", error.report()))
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct RepoConfig {
     #[serde(default = "default_branch_pattern")]
     pub branch_pattern: String,
+    /// Globs for changed files which are left out of the release commit.
+    #[serde(default)]
+    pub commit_exclude: Vec<String>,
+    /// Globs for files, beyond those changed by transforms, which are included in the release commit.
+    #[serde(default)]
+    pub commit_include: Vec<String>,
     #[serde(default = "default_commit_message")]
     pub commit_message: String,
     #[serde(default = "default_default_branch")]
@@ -39,8 +196,33 @@ pub struct RepoConfig {
     pub enable_push: bool,
     #[serde(default = "yes")]
     pub enable_tag: bool,
+    #[serde(default)]
+    pub infer_dependencies: bool,
+    #[serde(default = "default_initial_version")]
+    pub initial_version: Version,
+    #[serde(default)]
+    pub legacy_tag_patterns: Vec<String>,
+    #[serde(default)]
+    pub reachable_tags_only: bool,
+    /// The remote which releases are pushed to.
+    #[serde(default = "default_remote_name")]
+    pub remote_name: String,
+    /// Undoes the branch, commit and tag created by a release which fails part way through.
+    #[serde(default = "yes")]
+    pub rollback_on_failure: bool,
+    /// Also deletes refs which were already pushed when rolling back a release.
+    #[serde(default)]
+    pub rollback_remote: bool,
+    /// When false commits are never signed, even if git is configured to sign them.
+    #[serde(default = "yes")]
+    pub sign_commits: bool,
+    /// The ssh key used to authenticate with remotes, in place of the standard key in `~/.ssh`.
+    #[serde(default)]
+    pub ssh_key_path: Option<String>,
     #[serde(default = "default_tag_pattern")]
     pub tag_pattern: String,
+    #[serde(default)]
+    pub version_constraints: BTreeMap<String, String>,
 }
 
 fn default_branch_pattern() -> String {
@@ -55,6 +237,14 @@ fn default_default_branch() -> String {
     String::from("main")
 }
 
+fn default_initial_version() -> Version {
+    Version::new(0, 0, 1)
+}
+
+fn default_remote_name() -> String {
+    String::from("origin")
+}
+
 fn default_tag_pattern() -> String {
     String::from("{version}")
 }
@@ -67,18 +257,30 @@ impl Default for RepoConfig {
     fn default() -> Self {
         RepoConfig {
             branch_pattern: default_branch_pattern(),
+            commit_exclude: vec![],
+            commit_include: vec![],
             commit_message: default_commit_message(),
             default_branch: default_default_branch(),
             enable_branch: false,
             enable_commit: false,
             enable_tag: true,
             enable_push: true,
+            infer_dependencies: false,
+            initial_version: default_initial_version(),
+            legacy_tag_patterns: vec![],
+            reachable_tags_only: false,
+            remote_name: default_remote_name(),
+            rollback_on_failure: true,
+            rollback_remote: false,
+            sign_commits: true,
+            ssh_key_path: None,
             tag_pattern: default_tag_pattern(),
+            version_constraints: BTreeMap::new(),
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct TransformConfig {
     pub path: String,
     #[serde(default)]
@@ -86,32 +288,271 @@ pub struct TransformConfig {
     pub replace: String,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PackageConfig {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub branch_pattern: Option<String>,
+    #[serde(default)]
+    pub commit_message: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<DependencyConfig>,
+    #[serde(default)]
+    pub legacy_tag_patterns: Vec<String>,
+    #[serde(default)]
+    pub scripts: ScriptConfig,
+    #[serde(default)]
+    pub tag_pattern: Option<String>,
+    #[serde(default)]
+    pub transforms: Vec<TransformConfig>,
+}
+
+impl PackageConfig {
+    /// The package's tag pattern, defaulting to `<name>-{version}`.
+    pub fn tag_pattern(&self) -> String {
+        self.tag_pattern
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}", self.name, token::VERSION))
+    }
+
+    /// Resolves a path relative to the package against the repository root.
+    pub fn relative_path(&self, path: &str) -> String {
+        Path::new(&self.path).join(path).to_string_lossy().to_string()
+    }
+}
+
+/// Another package this package depends on. The transforms update the dependency requirement when
+/// the dependency is released; their paths are relative to the dependent package.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct DependencyConfig {
+    pub name: String,
+    #[serde(default)]
+    pub transforms: Vec<TransformConfig>,
+}
+
+/// Another repository released along with this project. By default the subproject receives the
+/// project's exact version; `bump` or `version_map` choose a different version instead.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct SubprojectConfig {
+    /// The branch or ref cloned and released, in place of the subproject's default branch.
+    #[serde(default, alias = "ref")]
+    pub branch: Option<String>,
+    /// Bumps the subproject's own latest version rather than using the project's version.
+    #[serde(default)]
+    pub bump: Option<BumpMode>,
+    /// A config file within the subproject, used in place of its `sheepit.toml`.
+    #[serde(default)]
+    pub config_path: Option<String>,
+    #[serde(default = "yes")]
+    pub enabled: bool,
+    /// A repository which is already checked out, released in place rather than cloned.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
     pub repo_url: String,
+    /// A template such as `{major}.{minor}.0` which maps the project's version to the subproject's.
+    #[serde(default)]
+    pub version_map: Option<String>,
+}
+
+impl SubprojectConfig {
+    /// Identifies the subproject by its local path, or by its repository url.
+    pub fn name(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.repo_url)
+    }
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+impl Default for SubprojectConfig {
+    fn default() -> Self {
+        SubprojectConfig {
+            branch: None,
+            bump: None,
+            config_path: None,
+            enabled: true,
+            path: None,
+            repo_url: String::new(),
+            version_map: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct ScriptConfig {
-    pub before_commit: Option<String>,
+    pub after_commit: Option<Script>,
+    pub after_push: Option<Script>,
+    pub after_tag: Option<Script>,
+    pub after_transforms: Option<Script>,
+    pub after_version_computed: Option<Script>,
+    pub before_all: Option<Script>,
+    pub before_branch: Option<Script>,
+    pub before_commit: Option<Script>,
+    pub before_push: Option<Script>,
+    pub before_tag: Option<Script>,
+    /// Runs when any step of the release fails, before the error is reported.
+    pub on_failure: Option<Script>,
+    /// The shell used to run string scripts, `sh` by default.
+    pub shell: Option<String>,
+}
+
+/// A script, either a command or a table with the command and its options.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Script {
+    Command(ScriptCommand),
+    Detailed(ScriptDetails),
+}
+
+/// A command run through the shell, or an argv array run directly without one.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ScriptCommand {
+    Shell(String),
+    Args(Vec<String>),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScriptDetails {
+    /// When true a failure is reported but doesn't stop the release.
+    #[serde(default)]
+    pub allow_failure: bool,
+    pub command: ScriptCommand,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// The shell used for a string command, in place of the `scripts.shell` setting.
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// Seconds after which the script is killed and the release fails.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// The directory the script runs in, relative to the repository root.
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+impl Script {
+    /// The script's options, with defaults for a script given as a plain command.
+    pub fn details(&self) -> ScriptDetails {
+        match self {
+            Script::Command(command) => ScriptDetails::new(command.clone()),
+            Script::Detailed(details) => details.clone(),
+        }
+    }
+}
+
+impl ScriptDetails {
+    pub fn new(command: ScriptCommand) -> Self {
+        ScriptDetails {
+            allow_failure: false,
+            command,
+            env: BTreeMap::new(),
+            shell: None,
+            timeout: None,
+            working_dir: None,
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+}
+
+impl From<&str> for Script {
+    fn from(value: &str) -> Self {
+        Script::Command(ScriptCommand::Shell(value.to_string()))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::config::{Config, RepoConfig, ScriptConfig, SubprojectConfig, TransformConfig};
+    use crate::config::{
+        Config, DependencyConfig, PackageConfig, RepoConfig, Script, ScriptCommand, ScriptConfig,
+        ScriptDetails, SubprojectConfig, TransformConfig,
+    };
+    use crate::project::operation::BumpMode;
+    use semver::Version;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn pattern_problems() {
+        let config = Config {
+            packages: vec![PackageConfig {
+                name: "web".to_string(),
+                path: "packages/web".to_string(),
+                branch_pattern: Some("release/web".to_string()),
+                tag_pattern: Some("web".to_string()),
+                ..Default::default()
+            }],
+            repository: RepoConfig {
+                branch_pattern: "release".to_string(),
+                enable_branch: true,
+                legacy_tag_patterns: vec!["v{version}".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected = vec![
+            "repository.branch_pattern 'release' must contain {version}".to_string(),
+            "package web tag_pattern 'web' must contain {version}".to_string(),
+            "package web branch_pattern 'release/web' must contain {version}".to_string(),
+        ];
+        assert_eq!(expected, config.pattern_problems())
+    }
+
+    #[test]
+    fn pattern_problems_regex_legacy_patterns() {
+        let config = Config {
+            repository: RepoConfig {
+                legacy_tag_patterns: vec![
+                    r"regex:^release-(?P<version>\d+\.\d+\.\d+)$".to_string(),
+                    "regex:release-(".to_string(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let problems = config.pattern_problems();
+        assert_eq!(1, problems.len());
+        assert!(problems[0].starts_with("repository.legacy_tag_patterns 'regex:release-('"))
+    }
+
+    #[test]
+    fn pattern_problems_branches_disabled() {
+        let config = Config {
+            repository: RepoConfig {
+                branch_pattern: "release".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.pattern_problems().is_empty())
+    }
 
     #[test]
     fn default_config() {
         let expected = Config {
+            packages: vec![],
             repository: RepoConfig {
                 branch_pattern: String::from("release/{version}"),
+                commit_exclude: vec![],
+                commit_include: vec![],
                 commit_message: String::from("preparing release {version}"),
                 default_branch: String::from("main"),
                 enable_branch: false,
                 enable_commit: false,
                 enable_tag: true,
                 enable_push: true,
+                infer_dependencies: false,
+                initial_version: Version::new(0, 0, 1),
+                legacy_tag_patterns: vec![],
+                reachable_tags_only: false,
+                remote_name: String::from("origin"),
+                rollback_on_failure: true,
+                rollback_remote: false,
+                sign_commits: true,
+                ssh_key_path: None,
                 tag_pattern: String::from("{version}"),
+                version_constraints: BTreeMap::new(),
             },
             scripts: ScriptConfig::default(),
             subprojects: vec![],
@@ -139,26 +580,87 @@ mod test {
         assert_eq!(expected, config)
     }
 
+    #[test]
+    fn from_toml_invalid_initial_version() {
+        let result: Result<Config, _> = toml::from_str(
+            r"
+        [repository]
+        initial_version = 'one'
+        ",
+        );
+        result.expect_err("initial version should fail to parse");
+    }
+
     #[test]
     fn from_toml_full_config() {
         let config: Config = toml::from_str(
             r"
+        [[packages]]
+        name = 'api'
+        path = 'packages/api'
+        tag_pattern = 'api/v{version}'
+
+        [[packages.dependencies]]
+        name = 'core'
+
+        [[packages.dependencies.transforms]]
+        path = 'Cargo.toml'
+        replace = 'core = {version}'
+
+        [[packages.transforms]]
+        path = 'Cargo.toml'
+        replace = 'version = {version}'
+
         [repository]
         branch_pattern = 'branch'
+        commit_exclude = ['*.log']
+        commit_include = ['dist/**']
         commit_message = 'commit'
         default_branch = 'dev'
         enable_branch = true
         enable_commit = true
         enable_tag = false
         enable_push = false
+        infer_dependencies = true
+        initial_version = '1.0.0'
+        legacy_tag_patterns = ['legacy-{version}']
+        reachable_tags_only = true
+        remote_name = 'upstream'
+        rollback_on_failure = false
+        rollback_remote = true
+        sign_commits = false
+        ssh_key_path = '~/.ssh/release_key'
         tag_pattern = 'tag'
 
+        [repository.version_constraints]
+        'release/1.8' = '1.8.*'
+
         [scripts]
+        after_push = 'echo pushed'
         before_commit = 'echo hello'
+        shell = 'bash'
+
+        [scripts.on_failure]
+        allow_failure = true
+        command = ['notify', '{version}']
+        env = { LEVEL = 'error' }
+        timeout = 30
+        working_dir = 'scripts'
 
         [[subprojects]]
         repo_url = 'https://api.example.com'
 
+        [[subprojects]]
+        bump = 'minor'
+        config_path = 'config/sheepit.toml'
+        enabled = false
+        ref = 'release/1.x'
+        repo_url = 'https://sdk.example.com'
+        version_map = '{major}.{minor}.0'
+
+        [[subprojects]]
+        path = '../sdk-ios'
+
         [[transforms]]
         path = 'path_1'
         find = 'find_1'
@@ -172,22 +674,85 @@ mod test {
         .expect("failed to parse config");
 
         let expected = Config {
+            packages: vec![PackageConfig {
+                name: "api".to_string(),
+                path: "packages/api".to_string(),
+                tag_pattern: Some("api/v{version}".to_string()),
+                dependencies: vec![DependencyConfig {
+                    name: "core".to_string(),
+                    transforms: vec![TransformConfig {
+                        path: "Cargo.toml".to_string(),
+                        find: None,
+                        replace: "core = {version}".to_string(),
+                    }],
+                }],
+                transforms: vec![TransformConfig {
+                    path: "Cargo.toml".to_string(),
+                    find: None,
+                    replace: "version = {version}".to_string(),
+                }],
+                ..Default::default()
+            }],
             repository: RepoConfig {
                 branch_pattern: "branch".to_string(),
+                commit_exclude: vec!["*.log".to_string()],
+                commit_include: vec!["dist/**".to_string()],
                 commit_message: "commit".to_string(),
                 default_branch: "dev".to_string(),
                 enable_branch: true,
                 enable_commit: true,
                 enable_push: false,
                 enable_tag: false,
+                infer_dependencies: true,
+                initial_version: Version::new(1, 0, 0),
+                legacy_tag_patterns: vec!["legacy-{version}".to_string()],
+                reachable_tags_only: true,
+                remote_name: "upstream".to_string(),
+                rollback_on_failure: false,
+                rollback_remote: true,
+                sign_commits: false,
+                ssh_key_path: Some("~/.ssh/release_key".to_string()),
                 tag_pattern: "tag".to_string(),
+                version_constraints: BTreeMap::from([(
+                    "release/1.8".to_string(),
+                    "1.8.*".to_string(),
+                )]),
             },
             scripts: ScriptConfig {
-                before_commit: "echo hello".to_string().into(),
+                after_push: Some(Script::from("echo pushed")),
+                before_commit: Some(Script::from("echo hello")),
+                on_failure: Some(Script::Detailed(ScriptDetails {
+                    allow_failure: true,
+                    env: BTreeMap::from([("LEVEL".to_string(), "error".to_string())]),
+                    timeout: Some(30),
+                    working_dir: Some("scripts".to_string()),
+                    ..ScriptDetails::new(ScriptCommand::Args(vec![
+                        "notify".to_string(),
+                        "{version}".to_string(),
+                    ]))
+                })),
+                shell: Some("bash".to_string()),
+                ..Default::default()
             },
-            subprojects: vec![SubprojectConfig {
-                repo_url: "https://api.example.com".to_string(),
-            }],
+            subprojects: vec![
+                SubprojectConfig {
+                    repo_url: "https://api.example.com".to_string(),
+                    ..Default::default()
+                },
+                SubprojectConfig {
+                    branch: Some("release/1.x".to_string()),
+                    bump: Some(BumpMode::Minor),
+                    config_path: Some("config/sheepit.toml".to_string()),
+                    enabled: false,
+                    repo_url: "https://sdk.example.com".to_string(),
+                    version_map: Some("{major}.{minor}.0".to_string()),
+                    ..Default::default()
+                },
+                SubprojectConfig {
+                    path: Some("../sdk-ios".to_string()),
+                    ..Default::default()
+                },
+            ],
             transforms: vec![
                 TransformConfig {
                     path: "path_1".to_string(),
@@ -203,4 +768,81 @@ mod test {
         };
         assert_eq!(expected, config)
     }
+
+    #[test]
+    fn with_subproject_paths_from() {
+        let config = Config {
+            subprojects: vec![
+                SubprojectConfig {
+                    path: Some("../sdk-ios".to_string()),
+                    ..Default::default()
+                },
+                SubprojectConfig {
+                    repo_url: "https://api.example.com".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let resolved = config.with_subproject_paths_from("/work/app");
+        let paths: Vec<_> = resolved.subprojects.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(vec![Some("/work/app/../sdk-ios".to_string()), None], paths)
+    }
+
+    #[test]
+    fn package_not_found() {
+        let config = Config::default();
+        config.package("missing").expect_err("package should not exist");
+    }
+
+    #[test]
+    fn for_package() {
+        let package = PackageConfig {
+            name: "web".to_string(),
+            path: "packages/web".to_string(),
+            scripts: ScriptConfig {
+                before_commit: Some(Script::from("npm test")),
+                ..Default::default()
+            },
+            transforms: vec![TransformConfig {
+                path: "package.json".to_string(),
+                find: None,
+                replace: "\"version\": \"{version}\"".to_string(),
+            }],
+            ..Default::default()
+        };
+        let config = Config {
+            packages: vec![package.clone()],
+            repository: RepoConfig {
+                enable_commit: true,
+                ..Default::default()
+            },
+            subprojects: vec![SubprojectConfig {
+                repo_url: "https://api.example.com".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let expected = Config {
+            packages: vec![],
+            repository: RepoConfig {
+                enable_commit: true,
+                tag_pattern: "web-{version}".to_string(),
+                ..Default::default()
+            },
+            scripts: ScriptConfig {
+                before_commit: Some(Script::from("npm test")),
+                ..Default::default()
+            },
+            subprojects: vec![],
+            transforms: vec![TransformConfig {
+                path: "packages/web/package.json".to_string(),
+                find: None,
+                replace: "\"version\": \"{version}\"".to_string(),
+            }],
+        };
+        assert_eq!(expected, config.for_package(&package))
+    }
 }
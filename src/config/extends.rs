@@ -0,0 +1,244 @@
+use crate::config::ConfigSource;
+use crate::project::temp;
+use crate::repo::clone::GitCloner;
+use crate::SheepError;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use toml::{Table, Value};
+
+pub const EXTENDS_KEY: &str = "extends";
+const GIT_PREFIX: &str = "git+";
+const DEFAULT_GIT_PATH: &str = "sheepit.toml";
+
+/// A config which another config extends.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BaseConfig {
+    /// A local file. Relative paths are relative to the repository of the extending config.
+    File(PathBuf),
+    /// A file within a git repository, which is cloned to read it.
+    Git {
+        url: String,
+        branch: Option<String>,
+        path: PathBuf,
+    },
+}
+
+impl BaseConfig {
+    /// Parses a local path or a url such as `git+ssh://host/org/presets.git#path=cargo.toml`.
+    /// The url's fragment may set the `path` of the config, `sheepit.toml` by default, and the
+    /// `branch` to clone.
+    pub fn parse(text: &str, root: &Path) -> Result<BaseConfig, SheepError> {
+        let Some(git_url) = text.strip_prefix(GIT_PREFIX) else {
+            return Ok(BaseConfig::File(root.join(text)));
+        };
+        let (url, fragment) = git_url.split_once('#').unwrap_or((git_url, ""));
+        let mut branch = None;
+        let mut path = PathBuf::from(DEFAULT_GIT_PATH);
+        for parameter in fragment.split('&').filter(|parameter| !parameter.is_empty()) {
+            match parameter.split_once('=') {
+                Some(("branch", value)) => branch = Some(value.to_string()),
+                Some(("path", value)) => path = PathBuf::from(value),
+                _ => {
+                    let message = format!("unknown parameter {parameter} in extends {text}");
+                    return Err(SheepError::config(&message));
+                }
+            }
+        }
+        Ok(BaseConfig::Git { url: url.to_string(), branch, path })
+    }
+
+    /// Identifies the config, so a config which ends up extending itself can be detected.
+    pub fn key(&self) -> String {
+        match self {
+            BaseConfig::File(path) => file_key(path),
+            BaseConfig::Git { .. } => self.to_string(),
+        }
+    }
+
+    pub fn source(&self) -> ConfigSource {
+        match self {
+            BaseConfig::File(path) => ConfigSource::File(path.clone()),
+            BaseConfig::Git { .. } => ConfigSource::Git(self.to_string()),
+        }
+    }
+
+    /// Fetches the config, returning the file to read and the root which the config's own
+    /// relative `extends` paths are relative to.
+    pub fn fetch(&self,
+                 root: &Path,
+                 clones: &mut BaseConfigClones,
+                 ssh_key_path: Option<&str>) -> Result<(PathBuf, PathBuf), SheepError> {
+        match self {
+            BaseConfig::File(path) => Ok((path.clone(), root.to_path_buf())),
+            BaseConfig::Git { url, branch, path } => {
+                let repo_path = clones.clone(url, branch.as_deref(), ssh_key_path)?;
+                Ok((repo_path.join(path), repo_path))
+            }
+        }
+    }
+}
+
+/// The repositories cloned to read git hosted configs, so each is only cloned once while a
+/// config is opened. The clones are deleted when this is dropped.
+#[derive(Default)]
+pub struct BaseConfigClones {
+    clones: BTreeMap<String, TempDir>,
+}
+
+impl BaseConfigClones {
+    fn clone(&mut self,
+             url: &str,
+             branch: Option<&str>,
+             ssh_key_path: Option<&str>) -> Result<PathBuf, SheepError> {
+        let key = match branch {
+            Some(branch) => format!("{url}#branch={branch}"),
+            None => url.to_string(),
+        };
+        if let Some(clone) = self.clones.get(&key) {
+            return Ok(clone.path().to_path_buf());
+        }
+        // Progress goes to stderr so it stays out of output such as `config show`
+        eprintln!("📥 fetching base configs from {key}");
+        let clone = temp::scoped_directory()?;
+        GitCloner::new()
+            .with_branch(branch)
+            .with_ssh_key_path(ssh_key_path)
+            .clone(url, clone.path())
+            .map_err(|error| {
+                let message = format!("failed to fetch base configs from {key}");
+                SheepError::config(&message).with_source(error)
+            })?;
+        let repo_path = clone.path().to_path_buf();
+        self.clones.insert(key, clone);
+        Ok(repo_path)
+    }
+}
+
+impl Display for BaseConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BaseConfig::File(path) => write!(f, "{}", path.display()),
+            BaseConfig::Git { url, branch, path } => {
+                write!(f, "{GIT_PREFIX}{url}#path={}", path.display())?;
+                match branch {
+                    Some(branch) => write!(f, "&branch={branch}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Identifies a local config by its canonical path, falling back to the path as given.
+pub fn file_key(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    path.to_string_lossy().to_string()
+}
+
+/// Removes `extends` from the config, returning the configs it names in the order given. It may
+/// be a single path or url, or a list of them.
+pub fn take_extends(table: &mut Table,
+                    root: &Path,
+                    source: &str) -> Result<Vec<BaseConfig>, SheepError> {
+    let texts = match table.remove(EXTENDS_KEY) {
+        None => vec![],
+        Some(Value::String(text)) => vec![Value::String(text)],
+        Some(Value::Array(texts)) => texts,
+        Some(_) => return Err(invalid_extends(source)),
+    };
+    texts
+        .iter()
+        .map(|text| match text {
+            Value::String(text) => BaseConfig::parse(text, root),
+            _ => Err(invalid_extends(source)),
+        })
+        .collect()
+}
+
+fn invalid_extends(source: &str) -> SheepError {
+    SheepError::config(&format!("extends in {source} must be a path, a url or a list of them"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::extends::{take_extends, BaseConfig, BaseConfigClones};
+    use crate::repo::testing;
+    use std::path::{Path, PathBuf};
+    use toml::Table;
+
+    #[test]
+    fn parse_file() {
+        let base = BaseConfig::parse("config/base.toml", Path::new("/repo")).unwrap();
+        assert_eq!(BaseConfig::File(PathBuf::from("/repo/config/base.toml")), base)
+    }
+
+    #[test]
+    fn parse_git() {
+        let text = "git+ssh://git@example.com/org/presets.git#path=cargo.toml&branch=v2";
+        let base = BaseConfig::parse(text, Path::new("/repo")).unwrap();
+        let expected = BaseConfig::Git {
+            url: "ssh://git@example.com/org/presets.git".to_string(),
+            branch: Some("v2".to_string()),
+            path: PathBuf::from("cargo.toml"),
+        };
+        assert_eq!(expected, base);
+        assert_eq!(text, base.to_string())
+    }
+
+    #[test]
+    fn parse_git_default_path() {
+        let base = BaseConfig::parse("git+https://example.com/presets.git", Path::new("/repo"));
+        let expected = BaseConfig::Git {
+            url: "https://example.com/presets.git".to_string(),
+            branch: None,
+            path: PathBuf::from("sheepit.toml"),
+        };
+        assert_eq!(expected, base.unwrap())
+    }
+
+    #[test]
+    fn parse_git_unknown_parameter() {
+        let text = "git+https://example.com/presets.git#file=cargo.toml";
+        BaseConfig::parse(text, Path::new("/repo")).expect_err("should fail to parse");
+    }
+
+    #[test]
+    fn fetch_git_clones_once() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "sheepit.toml", "[repository]");
+        testing::commit(&repo, "first");
+        let url = temp_dir.path().to_string_lossy().to_string();
+        let base = BaseConfig::parse(&format!("git+{url}"), Path::new("/repo")).unwrap();
+        let other = BaseConfig::parse(&format!("git+{url}#path=other.toml"), Path::new("/repo"));
+
+        let mut clones = BaseConfigClones::default();
+        let (path, root) = base.fetch(Path::new("/repo"), &mut clones, None).unwrap();
+        let (_, other_root) = other.unwrap().fetch(Path::new("/repo"), &mut clones, None).unwrap();
+        assert!(path.exists());
+        assert_eq!(root, other_root);
+
+        drop(clones);
+        assert!(!root.exists())
+    }
+
+    #[test]
+    fn take_extends_list() {
+        let mut table: Table = toml::from_str("extends = ['a.toml', 'b.toml']\n[repository]").unwrap();
+        let bases = take_extends(&mut table, Path::new("/repo"), "sheepit.toml").unwrap();
+        let expected = vec![
+            BaseConfig::File(PathBuf::from("/repo/a.toml")),
+            BaseConfig::File(PathBuf::from("/repo/b.toml")),
+        ];
+        assert_eq!(expected, bases);
+        assert!(!table.contains_key("extends"))
+    }
+
+    #[test]
+    fn take_extends_invalid() {
+        let mut table: Table = toml::from_str("extends = 1").unwrap();
+        take_extends(&mut table, Path::new("/repo"), "sheepit.toml").expect_err("should fail");
+    }
+}
@@ -1,6 +1,8 @@
+use std::env;
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use mockall_double::double;
+use crate::config::manifest::Manifest;
 #[double]
 use crate::file::FileChecker;
 
@@ -10,6 +12,25 @@ pub fn find_config<P: AsRef<Path>>(file_checker: &FileChecker, repo_path: P) ->
         .cloned()
 }
 
+/// The manifests in the repository which may embed a config, in the order they're searched.
+pub fn find_manifests<P: AsRef<Path>>(file_checker: &FileChecker, repo_path: P) -> Vec<PathBuf> {
+    Manifest::ALL
+        .iter()
+        .map(|manifest| repo_path.as_ref().join(manifest.file_name()))
+        .filter(|path| file_checker.file_exists(path))
+        .collect()
+}
+
+/// The user's config, which provides defaults for every repository. It lives in
+/// `$XDG_CONFIG_HOME/sheepit/config.toml`, or `~/.config/sheepit/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("sheepit").join("config.toml"))
+}
+
 fn config_paths<P: AsRef<Path>>(repo_path: P) -> Vec<PathBuf> {
     let base_path_ref = repo_path.as_ref();
     let base_path = base_path_ref.as_os_str().to_os_string();
@@ -21,14 +42,15 @@ fn config_paths<P: AsRef<Path>>(repo_path: P) -> Vec<PathBuf> {
 
 fn config_names() -> Vec<OsString> {
     vec![OsString::from("sheepit.toml"),
-         OsString::from(".sheepit.toml")]
+         OsString::from(".sheepit.toml"),
+         OsString::from(".config/sheepit.toml")]
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use mockall_double::double;
-    use crate::config::finder::find_config;
+    use crate::config::finder::{find_config, find_manifests};
     #[double]
     use crate::file::{FileChecker};
 
@@ -82,4 +104,30 @@ mod test {
         let expected = PathBuf::from("/path/.sheepit.toml");
         assert_eq!(Some(expected), config_path);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn find_config_in_config_directory() {
+        let mut file_checker = FileChecker::default();
+        file_checker.expect_file_exists()
+            .withf(|path| path.as_ref() == Path::new("/path/.config/sheepit.toml"))
+            .return_const(true);
+        file_checker.expect_file_exists().return_const(false);
+
+        let config_path = find_config(&file_checker, PATH);
+        let expected = PathBuf::from("/path/.config/sheepit.toml");
+        assert_eq!(Some(expected), config_path);
+    }
+
+    #[test]
+    fn find_manifests_in_search_order() {
+        let mut file_checker = FileChecker::default();
+        file_checker.expect_file_exists()
+            .withf(|path| path.as_ref() == Path::new("/path/pyproject.toml"))
+            .return_const(false);
+        file_checker.expect_file_exists().return_const(true);
+
+        let manifests = find_manifests(&file_checker, PATH);
+        let expected = vec![PathBuf::from("/path/Cargo.toml"), PathBuf::from("/path/package.json")];
+        assert_eq!(expected, manifests);
+    }
+}
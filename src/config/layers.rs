@@ -0,0 +1,297 @@
+use crate::config::Config;
+use crate::SheepError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+const ENV_PREFIX: &str = "SHEEPIT_";
+const ENV_SEPARATOR: &str = "__";
+
+/// Where a config value came from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    /// An environment variable, given by name.
+    Env(String),
+    File(PathBuf),
+    /// A config within a git repository, given by url.
+    Git(String),
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Env(name) => write!(f, "{name}"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Git(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Config values from several sources, where each layer overrides the layers added before it.
+/// Tables are merged key by key while other values, including arrays, are replaced.
+#[derive(Debug, Default)]
+pub struct ConfigLayers {
+    table: Table,
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+/// How arrays found in both the config and a new layer are combined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Arrays {
+    Append,
+    Replace,
+}
+
+impl ConfigLayers {
+    /// A single layer, where every value comes from `source`.
+    pub fn new(table: Table, source: ConfigSource) -> ConfigLayers {
+        let sources = table.keys().map(|key| (key.clone(), source.clone())).collect();
+        ConfigLayers { table, sources }
+    }
+
+    pub fn add(&mut self, layer: Table, source: ConfigSource) {
+        self.add_layers(ConfigLayers::new(layer, source))
+    }
+
+    /// Adds the merged layers as though they were one layer, keeping track of their sources.
+    pub fn add_layers(&mut self, layers: ConfigLayers) {
+        self.merge(layers, Arrays::Replace)
+    }
+
+    /// Adds the merged layers like [`ConfigLayers::add_layers`], but appends their arrays to
+    /// arrays already in the config. Configs extending another config are added this way, so
+    /// their transforms are added to the transforms they inherit.
+    pub fn extend(&mut self, layers: ConfigLayers) {
+        self.merge(layers, Arrays::Append)
+    }
+
+    fn merge(&mut self, layers: ConfigLayers, arrays: Arrays) {
+        let ConfigLayers { table, sources } = layers;
+        let mut merge = Merge { arrays, layer_sources: &sources, sources: &mut self.sources };
+        merge.tables(&mut self.table, table, "")
+    }
+
+    pub fn config(&self) -> Result<Config, SheepError> {
+        Ok(Config::deserialize(Value::Table(self.table.clone()))?)
+    }
+
+    /// Every value of the merged config, including defaults, keyed by its dotted path along with
+    /// the source it came from.
+    pub fn values(&self) -> Result<Vec<(String, Value, ConfigSource)>, SheepError> {
+        let mut values = vec![];
+        flatten(&Value::try_from(self.config()?)?, "", &mut values);
+        let values = values
+            .into_iter()
+            .map(|(key, value)| {
+                let source = self.source(&key);
+                (key, value, source)
+            })
+            .collect();
+        Ok(values)
+    }
+
+    fn source(&self, key: &str) -> ConfigSource {
+        source(&self.sources, key)
+    }
+}
+
+/// The source of the key, or of the closest table containing it.
+fn source(sources: &BTreeMap<String, ConfigSource>, key: &str) -> ConfigSource {
+    let mut key = key;
+    loop {
+        if let Some(source) = sources.get(key) {
+            return source.clone();
+        }
+        match key.rsplit_once('.') {
+            Some((parent, _)) => key = parent,
+            None => return ConfigSource::Default,
+        }
+    }
+}
+
+/// Converts `SHEEPIT_SECTION__KEY` environment variables into config tables, one for each
+/// variable. Values are parsed as TOML when they can be and are otherwise used as strings.
+/// Variables without a `__` separator, such as `SHEEPIT_SSH_KEY_PATH`, aren't config overrides.
+pub fn env_layers<I: IntoIterator<Item = (String, String)>>(vars: I) -> Vec<(String, Table)> {
+    let mut layers: Vec<(String, Table)> = vars
+        .into_iter()
+        .filter_map(|(name, text)| {
+            let keys = name.strip_prefix(ENV_PREFIX)?;
+            if !keys.contains(ENV_SEPARATOR) || keys.split(ENV_SEPARATOR).any(str::is_empty) {
+                return None;
+            }
+            let mut value = env_value(&text);
+            for key in keys.rsplit(ENV_SEPARATOR) {
+                value = Value::Table(Table::from_iter([(key.to_lowercase(), value)]));
+            }
+            match value {
+                Value::Table(table) => Some((name, table)),
+                _ => None,
+            }
+        })
+        .collect();
+    layers.sort_by(|(left, _), (right, _)| left.cmp(right));
+    layers
+}
+
+fn env_value(text: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {text}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+struct Merge<'a> {
+    arrays: Arrays,
+    layer_sources: &'a BTreeMap<String, ConfigSource>,
+    sources: &'a mut BTreeMap<String, ConfigSource>,
+}
+
+impl Merge<'_> {
+    fn tables(&mut self, base: &mut Table, layer: Table, prefix: &str) {
+        for (key, value) in layer {
+            let path = dotted_key(prefix, &key);
+            match (base.get_mut(&key), value) {
+                (Some(Value::Table(base_table)), Value::Table(layer_table)) => {
+                    self.tables(base_table, layer_table, &path)
+                }
+                // New tables are merged too, so their sources are recorded leaf by leaf
+                (None, Value::Table(layer_table)) => {
+                    let mut base_table = Table::new();
+                    self.tables(&mut base_table, layer_table, &path);
+                    base.insert(key, Value::Table(base_table));
+                }
+                (Some(Value::Array(base_array)), Value::Array(layer_array))
+                    if self.arrays == Arrays::Append => {
+                    base_array.extend(layer_array);
+                    self.record(path);
+                }
+                (_, value) => {
+                    base.insert(key, value);
+                    self.record(path);
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, path: String) {
+        // Anything recorded under the replaced value no longer applies
+        self.sources.retain(|key, _| !key.starts_with(&format!("{path}.")));
+        let source = source(self.layer_sources, &path);
+        self.sources.insert(path, source);
+    }
+}
+
+fn flatten(value: &Value, prefix: &str, values: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                flatten(value, &dotted_key(prefix, key), values);
+            }
+        }
+        value => values.push((prefix.to_string(), value.clone())),
+    }
+}
+
+fn dotted_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::layers::{env_layers, ConfigLayers, ConfigSource};
+    use std::path::PathBuf;
+    use toml::{Table, Value};
+
+    #[test]
+    fn add_merges_tables() {
+        let user = ConfigSource::File(PathBuf::from("user.toml"));
+        let repo = ConfigSource::File(PathBuf::from("sheepit.toml"));
+        let mut layers = ConfigLayers::default();
+        layers.add(table("[repository]\nremote_name = 'upstream'\nenable_tag = false"), user.clone());
+        layers.add(table("[repository]\nenable_tag = true"), repo.clone());
+
+        let config = layers.config().expect("failed to merge");
+        assert_eq!("upstream", config.repository.remote_name);
+        assert!(config.repository.enable_tag);
+
+        let values = layers.values().expect("failed to describe");
+        assert_eq!(Some(user), source(&values, "repository.remote_name"));
+        assert_eq!(Some(repo), source(&values, "repository.enable_tag"));
+        assert_eq!(Some(ConfigSource::Default), source(&values, "repository.tag_pattern"))
+    }
+
+    #[test]
+    fn add_replaces_arrays() {
+        let mut layers = ConfigLayers::default();
+        let user = ConfigSource::File(PathBuf::from("user.toml"));
+        layers.add(table("[repository]\ncommit_include = ['a', 'b']"), user);
+        layers.add(table("[repository]\ncommit_include = ['c']"), ConfigSource::Default);
+
+        let config = layers.config().expect("failed to merge");
+        assert_eq!(vec!["c".to_string()], config.repository.commit_include)
+    }
+
+    #[test]
+    fn extend_appends_arrays() {
+        let base = ConfigSource::File(PathBuf::from("base.toml"));
+        let repo = ConfigSource::File(PathBuf::from("sheepit.toml"));
+        let mut chain = ConfigLayers::default();
+        chain.extend(ConfigLayers::new(
+            table("[repository]\ncommit_include = ['a']\ntag_pattern = 'v{version}'"),
+            base.clone(),
+        ));
+        chain.extend(ConfigLayers::new(table("[repository]\ncommit_include = ['b']"), repo.clone()));
+        let mut layers = ConfigLayers::default();
+        let user = ConfigSource::File(PathBuf::from("user.toml"));
+        layers.add(table("[repository]\ncommit_include = ['user']"), user);
+        layers.add_layers(chain);
+
+        let config = layers.config().expect("failed to merge");
+        assert_eq!(vec!["a".to_string(), "b".to_string()], config.repository.commit_include);
+
+        let values = layers.values().expect("failed to describe");
+        assert_eq!(Some(base), source(&values, "repository.tag_pattern"));
+        assert_eq!(Some(repo), source(&values, "repository.commit_include"))
+    }
+
+    #[test]
+    fn env_layers_parses_overrides() {
+        let vars = vec![
+            ("SHEEPIT_REPOSITORY__ENABLE_TAG".to_string(), "false".to_string()),
+            ("SHEEPIT_REPOSITORY__TAG_PATTERN".to_string(), "v{version}".to_string()),
+            ("SHEEPIT_SSH_KEY_PATH".to_string(), "~/.ssh/key".to_string()),
+            ("HOME".to_string(), "/home/sheep".to_string()),
+        ];
+        let expected = vec![
+            (
+                "SHEEPIT_REPOSITORY__ENABLE_TAG".to_string(),
+                table("[repository]\nenable_tag = false"),
+            ),
+            (
+                "SHEEPIT_REPOSITORY__TAG_PATTERN".to_string(),
+                table("[repository]\ntag_pattern = 'v{version}'"),
+            ),
+        ];
+        assert_eq!(expected, env_layers(vars))
+    }
+
+    fn table(text: &str) -> Table {
+        toml::from_str(text).unwrap()
+    }
+
+    fn source(values: &[(String, Value, ConfigSource)], key: &str) -> Option<ConfigSource> {
+        values
+            .iter()
+            .find(|(value_key, _, _)| value_key == key)
+            .map(|(_, _, source)| source.clone())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::SheepError;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// An ecosystem manifest which may embed sheepit's config, listed in the order they're searched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Manifest {
+    /// `Cargo.toml`, with the config under `[package.metadata.sheepit]`.
+    Cargo,
+    /// `pyproject.toml`, with the config under `[tool.sheepit]`.
+    Pyproject,
+    /// `package.json`, with the config under the `"sheepit"` key.
+    Npm,
+}
+
+impl Manifest {
+    pub const ALL: [Manifest; 3] = [Manifest::Cargo, Manifest::Pyproject, Manifest::Npm];
+
+    /// The manifest a config path refers to, if it is one.
+    pub fn from_path(path: &Path) -> Option<Manifest> {
+        let file_name = path.file_name()?;
+        Manifest::ALL.into_iter().find(|manifest| file_name == manifest.file_name())
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Manifest::Cargo => "Cargo.toml",
+            Manifest::Pyproject => "pyproject.toml",
+            Manifest::Npm => "package.json",
+        }
+    }
+
+    /// Where the config lives within the manifest, as it appears in the manifest.
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            Manifest::Cargo => "[package.metadata.sheepit]",
+            Manifest::Pyproject => "[tool.sheepit]",
+            Manifest::Npm => "\"sheepit\"",
+        }
+    }
+
+    /// Reads the config embedded in the manifest's text, or `None` if it has none.
+    pub fn config_table(&self, text: &str) -> Result<Option<Table>, SheepError> {
+        let config = match self {
+            Manifest::Cargo => toml::from_str::<Table>(text)?
+                .remove("package")
+                .and_then(|package| nested(package, &["metadata", "sheepit"])),
+            Manifest::Pyproject => toml::from_str::<Table>(text)?
+                .remove("tool")
+                .and_then(|tool| nested(tool, &["sheepit"])),
+            Manifest::Npm => match serde_json::from_str::<serde_json::Value>(text)?.get("sheepit") {
+                Some(config) => Some(Value::try_from(config).map_err(|error| {
+                    let message = "\"sheepit\" in package.json can't be used as a config";
+                    SheepError::config(message).with_source(error)
+                })?),
+                None => None,
+            },
+        };
+        match config {
+            Some(Value::Table(table)) => Ok(Some(table)),
+            Some(_) => {
+                let (key, file_name) = (self.config_key(), self.file_name());
+                let message = format!("{key} in {file_name} must be a table");
+                Err(SheepError::config(&message))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whether the file is a manifest with an embedded config. Manifests which can't be read or parsed
+/// are treated as having none, since they may not belong to sheepit at all, but a manifest whose
+/// config key holds something other than a config is an error.
+pub fn has_embedded_config(path: &Path) -> Result<bool, SheepError> {
+    let Some(manifest) = Manifest::from_path(path) else {
+        return Ok(false);
+    };
+    let Ok(text) = fs::read_to_string(path) else {
+        return Ok(false);
+    };
+    let parses = match manifest {
+        Manifest::Cargo | Manifest::Pyproject => toml::from_str::<Table>(&text).is_ok(),
+        Manifest::Npm => serde_json::from_str::<serde_json::Value>(&text).is_ok(),
+    };
+    if !parses {
+        return Ok(false);
+    }
+    let config = manifest.config_table(&text).map_err(|error| {
+        let path = path.to_string_lossy();
+        SheepError::config(&format!("invalid config in {path}")).with_source(error)
+    })?;
+    Ok(config.is_some())
+}
+
+fn nested(value: Value, keys: &[&str]) -> Option<Value> {
+    keys.iter().try_fold(value, |value, key| match value {
+        Value::Table(mut table) => table.remove(*key),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::manifest::{has_embedded_config, Manifest};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use toml::Table;
+
+    #[test]
+    fn from_path() {
+        assert_eq!(Some(Manifest::Cargo), Manifest::from_path(Path::new("/repo/Cargo.toml")));
+        assert_eq!(Some(Manifest::Npm), Manifest::from_path(Path::new("package.json")));
+        assert_eq!(None, Manifest::from_path(Path::new("/repo/sheepit.toml")));
+    }
+
+    #[test]
+    fn config_table_cargo() {
+        let text = r#"
+        [package]
+        name = "sheep"
+        version = "1.0.0"
+
+        [package.metadata.sheepit.repository]
+        enable_branch = true
+        "#;
+        let config = Manifest::Cargo.config_table(text).expect("failed to read");
+        assert_eq!(Some(table("[repository]\nenable_branch = true")), config)
+    }
+
+    #[test]
+    fn config_table_pyproject() {
+        let text = r#"
+        [project]
+        name = "sheep"
+
+        [tool.sheepit.repository]
+        tag_pattern = 'v{version}'
+        "#;
+        let config = Manifest::Pyproject.config_table(text).expect("failed to read");
+        assert_eq!(Some(table("[repository]\ntag_pattern = 'v{version}'")), config)
+    }
+
+    #[test]
+    fn config_table_npm() {
+        let text = r#"{
+            "name": "sheep",
+            "sheepit": { "repository": { "enable_commit": true } }
+        }"#;
+        let config = Manifest::Npm.config_table(text).expect("failed to read");
+        assert_eq!(Some(table("[repository]\nenable_commit = true")), config)
+    }
+
+    #[test]
+    fn config_table_missing() {
+        let text = "[package]\nname = \"sheep\"\n";
+        assert_eq!(None, Manifest::Cargo.config_table(text).expect("failed to read"))
+    }
+
+    #[test]
+    fn config_table_not_a_table() {
+        let text = r#"{ "sheepit": true }"#;
+        Manifest::Npm.config_table(text).expect_err("should fail");
+    }
+
+    #[test]
+    fn has_embedded_config_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("package.json");
+        fs::write(&path, r#"{ "sheepit": { "repository": {} } }"#).unwrap();
+        assert!(has_embedded_config(&path).unwrap());
+        fs::write(&path, r#"{ "name": "sheep" }"#).unwrap();
+        assert!(!has_embedded_config(&path).unwrap());
+        fs::write(&path, "{ not json").unwrap();
+        assert!(!has_embedded_config(&path).unwrap());
+        fs::write(&path, r#"{ "sheepit": null }"#).unwrap();
+        has_embedded_config(&path).expect_err("should fail");
+
+        let path = temp_dir.path().join("Cargo.toml");
+        fs::write(&path, "[package.metadata]\nsheepit = true").unwrap();
+        has_embedded_config(&path).expect_err("should fail");
+    }
+
+    fn table(text: &str) -> Table {
+        toml::from_str(text).unwrap()
+    }
+}
@@ -1,25 +1,378 @@
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
 use mockall_double::double;
+use toml::{Table, Value};
 use crate::config::Config;
-use crate::config::finder::find_config;
+use crate::config::extends::{file_key, take_extends, BaseConfigClones, EXTENDS_KEY};
+use crate::config::finder::{find_config, find_manifests, user_config_path};
+use crate::config::layers::{env_layers, ConfigLayers, ConfigSource};
+use crate::config::manifest::{has_embedded_config, Manifest};
 #[double]
 use crate::file::FileChecker;
 use crate::SheepError;
 
-pub fn open_config<P: AsRef<Path>>(repo_path: P) -> Result<Config, SheepError> {
-    let config_text = read_config(repo_path);
-    match config_text {
-        None => Ok(Config::default()),
-        Some(text) => {
-            let config: Config = toml::from_str(&text)?;
-            Ok(config)
+/// How keys which don't belong to the config are handled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnknownKeys {
+    /// Prints a warning for each unknown key and otherwise ignores it.
+    Warn,
+    /// Fails, listing every unknown key.
+    Deny,
+}
+
+/// Opens the layers making up the repository's config: the user's config, then the repository's
+/// config (or `config_path` in its place) and finally `SHEEPIT_SECTION__KEY` environment
+/// variables. Each layer is checked on its own so problems are reported against their source.
+/// Configs which extend other configs are read together with them as a single layer.
+///
+/// The repository's config is the first of `sheepit.toml`, `.sheepit.toml` and
+/// `.config/sheepit.toml` to exist, or failing that the config embedded in the first of
+/// `Cargo.toml`, `pyproject.toml` and `package.json` which embeds one.
+pub fn open_config_layers<P: AsRef<Path>>(
+    repo_path: P,
+    config_path: Option<&Path>,
+    unknown_keys: UnknownKeys,
+) -> Result<ConfigLayers, SheepError> {
+    let env_layers = env_layers(env::vars());
+    let mut bases = BaseConfigs {
+        ssh_key_path: env_layers.iter().find_map(|(_, layer)| ssh_key_path(layer)),
+        ..BaseConfigs::default()
+    };
+
+    let mut layers = ConfigLayers::default();
+    if let Some(user_path) = user_config_path().filter(|path| path.exists()) {
+        let root = user_path.parent().unwrap_or(&user_path).to_path_buf();
+        layers.add_layers(read_config(&user_path, &root, unknown_keys, &mut bases)?);
+        if bases.ssh_key_path.is_none() {
+            bases.ssh_key_path = layers.config()?.repository.ssh_key_path;
         }
     }
+
+    let repo_config_path = match config_path {
+        Some(config_path) => Some(config_path.to_path_buf()),
+        None => find_repo_config(&repo_path)?,
+    };
+    match repo_config_path {
+        Some(config_path) => {
+            let root = repo_path.as_ref();
+            layers.add_layers(read_config(&config_path, root, unknown_keys, &mut bases)?);
+        }
+        None if unknown_keys == UnknownKeys::Deny => {
+            let path = repo_path.as_ref().to_string_lossy();
+            return Err(SheepError::config(&format!("no sheepit config found in {path}")));
+        }
+        None => {}
+    }
+
+    for (name, layer) in env_layers {
+        check_layer(&layer, &name, unknown_keys)?;
+        layers.add(layer, ConfigSource::Env(name));
+    }
+    Ok(layers)
 }
 
-fn read_config<P: AsRef<Path>>(repo_path: P) -> Option<String> {
+/// Finds the repository's config: the first of `sheepit.toml`, `.sheepit.toml` and
+/// `.config/sheepit.toml` to exist, or failing that the first of `Cargo.toml`, `pyproject.toml`
+/// and `package.json` which embeds a config.
+pub fn find_repo_config<P: AsRef<Path>>(repo_path: P) -> Result<Option<PathBuf>, SheepError> {
     let file_checker = FileChecker::new();
-    let config_path = find_config(&file_checker, repo_path);
-    config_path.and_then(|path| fs::read_to_string(path).ok())
-}
\ No newline at end of file
+    if let Some(config_path) = find_config(&file_checker, &repo_path) {
+        return Ok(Some(config_path));
+    }
+    for manifest_path in find_manifests(&file_checker, &repo_path) {
+        if has_embedded_config(&manifest_path)? {
+            return Ok(Some(manifest_path));
+        }
+    }
+    Ok(None)
+}
+
+/// What's carried along while reading the configs which a config extends.
+#[derive(Default)]
+struct BaseConfigs {
+    /// The keys of the configs being read, so a config which extends itself is detected.
+    extending: Vec<String>,
+    clones: BaseConfigClones,
+    /// The ssh key git hosted configs are cloned with, unless the extending config sets one.
+    ssh_key_path: Option<String>,
+}
+
+/// Reads the config along with the configs it extends, which are added first so the config
+/// overrides them. Relative paths to extended configs are relative to `root`.
+fn read_config(config_path: &Path,
+               root: &Path,
+               unknown_keys: UnknownKeys,
+               bases: &mut BaseConfigs) -> Result<ConfigLayers, SheepError> {
+    bases.extending = vec![file_key(config_path)];
+    let source = ConfigSource::File(config_path.to_path_buf());
+    read_extended_config(config_path, source, root, unknown_keys, bases)
+}
+
+fn read_extended_config(config_path: &Path,
+                        source: ConfigSource,
+                        root: &Path,
+                        unknown_keys: UnknownKeys,
+                        bases: &mut BaseConfigs) -> Result<ConfigLayers, SheepError> {
+    let path = config_path.to_string_lossy();
+    let mut layer = read_layer(config_path, unknown_keys)?;
+    let mut layers = ConfigLayers::default();
+    let ssh_key_path = ssh_key_path(&layer).or_else(|| bases.ssh_key_path.clone());
+    for base in take_extends(&mut layer, root, &path)? {
+        let key = base.key();
+        if bases.extending.contains(&key) {
+            let message = format!("config {path} extends {base}, which extends it");
+            return Err(SheepError::config(&message));
+        }
+        let (base_path, base_root) =
+            base.fetch(root, &mut bases.clones, ssh_key_path.as_deref())?;
+        bases.extending.push(key);
+        let base_layers =
+            read_extended_config(&base_path, base.source(), &base_root, unknown_keys, bases)?;
+        layers.extend(base_layers);
+        bases.extending.pop();
+    }
+    layers.extend(ConfigLayers::new(layer, source));
+    Ok(layers)
+}
+
+fn read_layer(config_path: &Path, unknown_keys: UnknownKeys) -> Result<Table, SheepError> {
+    let path = config_path.to_string_lossy();
+    let text = fs::read_to_string(config_path).map_err(|error| {
+        SheepError::config(&format!("failed to read config {path}")).with_source(error)
+    })?;
+    if let Some(manifest) = Manifest::from_path(config_path) {
+        return read_embedded_layer(manifest, &text, &path, unknown_keys);
+    }
+    // Parsing as a config first reports mistakes with their line and column
+    let (_, ignored_keys) = parse_config(&text, &path)?;
+    report_unknown_keys(&ignored_keys, &path, unknown_keys)?;
+    Ok(toml::from_str(&text)?)
+}
+
+fn read_embedded_layer(manifest: Manifest,
+                       text: &str,
+                       path: &str,
+                       unknown_keys: UnknownKeys) -> Result<Table, SheepError> {
+    let config_key = manifest.config_key();
+    let layer = manifest
+        .config_table(text)
+        .map_err(|error| {
+            SheepError::config(&format!("failed to read config {path}")).with_source(error)
+        })?
+        .ok_or_else(|| SheepError::config(&format!("no {config_key} config in {path}")))?;
+    check_layer(&layer, &format!("{path} {config_key}"), unknown_keys)?;
+    Ok(layer)
+}
+
+fn ssh_key_path(layer: &Table) -> Option<String> {
+    let ssh_key_path = layer.get("repository")?.get("ssh_key_path")?.as_str()?;
+    Some(ssh_key_path.to_string())
+}
+
+fn check_layer(layer: &Table, name: &str, unknown_keys: UnknownKeys) -> Result<(), SheepError> {
+    let mut ignored_keys = vec![];
+    serde_ignored::deserialize::<_, _, Config>(Value::Table(layer.clone()), |key| {
+        ignored_keys.push(key.to_string())
+    })
+    .map_err(|error| SheepError::config(&format!("invalid config in {name}")).with_source(error))?;
+    report_unknown_keys(&ignored_keys, name, unknown_keys)
+}
+
+fn report_unknown_keys(ignored_keys: &[String],
+                       source: &str,
+                       unknown_keys: UnknownKeys) -> Result<(), SheepError> {
+    // `extends` is read before the config is, so it isn't part of the config itself
+    let ignored_keys: Vec<&str> = ignored_keys
+        .iter()
+        .map(String::as_str)
+        .filter(|key| *key != EXTENDS_KEY)
+        .collect();
+    if ignored_keys.is_empty() {
+        return Ok(());
+    }
+    match unknown_keys {
+        UnknownKeys::Warn => {
+            for key in ignored_keys {
+                println!("⚠️  ignoring unknown key {key} in {source}");
+            }
+            Ok(())
+        }
+        UnknownKeys::Deny => {
+            let message = format!("unknown keys in {source}: {}", ignored_keys.join(", "));
+            Err(SheepError::config(&message))
+        }
+    }
+}
+
+/// Parses the config, returning it along with the dotted path of each key which was ignored.
+fn parse_config(text: &str, path: &str) -> Result<(Config, Vec<String>), SheepError> {
+    let mut ignored_keys = vec![];
+    let deserializer = toml::Deserializer::new(text);
+    let config = serde_ignored::deserialize(deserializer, |key| {
+        ignored_keys.push(key.to_string())
+    })
+    .map_err(|error| {
+        let location = match error.span() {
+            Some(span) => {
+                let (line, column) = line_column(text, span.start);
+                format!("{path}:{line}:{column}")
+            }
+            None => path.to_string(),
+        };
+        SheepError::config(&format!("invalid config {location}: {}", error.message().trim()))
+    })?;
+    Ok((config, ignored_keys))
+}
+
+/// Converts a byte offset into a one based line and column.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::opener::{
+        check_layer, line_column, parse_config, read_config, read_layer, BaseConfigs, UnknownKeys,
+    };
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_config_unknown_keys() {
+        let text = r#"
+        [repository]
+        enable_comit = true
+
+        [[transforms]]
+        path = 'Cargo.toml'
+        replace = 'version = "{version}"'
+        fnd = 'version'
+        "#;
+        let (config, ignored_keys) = parse_config(text, "sheepit.toml").expect("failed to parse");
+        assert_eq!(1, config.transforms.len());
+        assert_eq!(vec!["repository.enable_comit", "transforms.0.fnd"], ignored_keys)
+    }
+
+    #[test]
+    fn parse_config_error_location() {
+        let text = "[repository]\nenable_commit = 'yes'\n";
+        let error = parse_config(text, "sheepit.toml").expect_err("should fail to parse");
+        assert!(error.message().starts_with("invalid config sheepit.toml:2:17: "))
+    }
+
+    #[test]
+    fn read_layer_deny_unknown_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sheepit.toml");
+        fs::write(&path, "[repository]\nenable_comit = true\n").unwrap();
+
+        let layer = read_layer(&path, UnknownKeys::Warn).expect("unknown keys should only warn");
+        assert!(layer.contains_key("repository"));
+        let error = read_layer(&path, UnknownKeys::Deny).expect_err("should fail");
+        assert!(error.message().ends_with("repository.enable_comit"))
+    }
+
+    #[test]
+    fn read_layer_embedded() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Cargo.toml");
+        let text = r#"
+        [package]
+        name = 'sheep'
+
+        [package.metadata.sheepit.repository]
+        enable_tg = true
+        "#;
+        fs::write(&path, text).unwrap();
+
+        let layer = read_layer(&path, UnknownKeys::Warn).expect("failed to read");
+        let expected: toml::Table = toml::from_str("[repository]\nenable_tg = true").unwrap();
+        assert_eq!(expected, layer);
+        let error = read_layer(&path, UnknownKeys::Deny).expect_err("should fail");
+        assert!(error.message().ends_with("[package.metadata.sheepit]: repository.enable_tg"));
+
+        fs::write(&path, "[package]\nname = 'sheep'\n").unwrap();
+        let error = read_layer(&path, UnknownKeys::Warn).expect_err("should fail");
+        assert!(error.message().starts_with("no [package.metadata.sheepit] config in "))
+    }
+
+    #[test]
+    fn read_layer_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sheepit.toml");
+        read_layer(&path, UnknownKeys::Warn).expect_err("should fail to read");
+    }
+
+    #[test]
+    fn read_config_extends() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("config")).unwrap();
+        let base = r#"
+        [repository]
+        enable_branch = true
+        enable_comit = true
+
+        [[transforms]]
+        path = 'README.md'
+        replace = '{version}'
+        "#;
+        fs::write(root.join("config/base.toml"), base).unwrap();
+        let config = r#"
+        extends = ['config/base.toml']
+
+        [repository]
+        enable_branch = false
+
+        [[transforms]]
+        path = 'Cargo.toml'
+        replace = 'version = "{version}"'
+        "#;
+        fs::write(root.join("sheepit.toml"), config).unwrap();
+        let config_path = root.join("sheepit.toml");
+
+        let mut bases = BaseConfigs::default();
+        let layers = read_config(&config_path, root, UnknownKeys::Warn, &mut bases)
+            .expect("failed to read");
+        let config = layers.config().unwrap();
+        assert!(!config.repository.enable_branch);
+        let paths: Vec<&str> = config.transforms.iter().map(|t| t.path.as_str()).collect();
+        assert_eq!(vec!["README.md", "Cargo.toml"], paths);
+
+        let error = read_config(&config_path, root, UnknownKeys::Deny, &mut bases)
+            .expect_err("should fail");
+        assert!(error.message().ends_with("repository.enable_comit"))
+    }
+
+    #[test]
+    fn read_config_extends_itself() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("sheepit.toml"), "extends = 'base.toml'").unwrap();
+        fs::write(root.join("base.toml"), "extends = ['sheepit.toml']").unwrap();
+
+        let config_path = root.join("sheepit.toml");
+        let mut bases = BaseConfigs::default();
+        let error = read_config(&config_path, root, UnknownKeys::Warn, &mut bases)
+            .expect_err("should fail");
+        assert!(error.message().contains("which extends it"))
+    }
+
+    #[test]
+    fn check_layer_invalid_value() {
+        let layer = toml::from_str("[repository]\nenable_tag = 'maybe'").unwrap();
+        let error = check_layer(&layer, "SHEEPIT_REPOSITORY__ENABLE_TAG", UnknownKeys::Warn)
+            .expect_err("should fail");
+        assert_eq!("invalid config in SHEEPIT_REPOSITORY__ENABLE_TAG", error.message())
+    }
+
+    #[test]
+    fn line_column_offsets() {
+        let text = "first\nsecond";
+        assert_eq!((1, 1), line_column(text, 0));
+        assert_eq!((2, 3), line_column(text, 8));
+    }
+}
@@ -0,0 +1,271 @@
+use crate::config::manifest::Manifest;
+use crate::config::{default_default_branch, default_remote_name, default_tag_pattern, Config,
+                    TransformConfig};
+use crate::repo::branch::GitBranches;
+use crate::repo::tag::GitTags;
+use crate::{prompt, token, SheepError};
+use git2::Repository;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+const CONFIG_NAME: &str = "sheepit.toml";
+
+/// A starting config proposed from what's already in a repository.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigScaffold {
+    pub default_branch: String,
+    pub tag_pattern: String,
+    /// Transforms for the manifests whose version could be found.
+    pub transforms: Vec<TransformConfig>,
+}
+
+impl ConfigScaffold {
+    /// Proposes a config from the repository's manifests, the style of its existing tags and its
+    /// default branch.
+    pub fn detect(repo: &Repository, repo_path: &Path) -> Result<ConfigScaffold, SheepError> {
+        let default_branch = GitBranches::new()
+            .default_branch(repo, &default_remote_name())
+            .unwrap_or_else(default_default_branch);
+        let tags = GitTags::new().get_tags(repo)?;
+        let transforms = Manifest::ALL
+            .iter()
+            .filter_map(|manifest| {
+                let text = fs::read_to_string(repo_path.join(manifest.file_name())).ok()?;
+                version_transform(*manifest, &text)
+            })
+            .collect();
+        Ok(ConfigScaffold {
+            default_branch,
+            tag_pattern: tag_pattern(&tags),
+            transforms,
+        })
+    }
+
+    /// Asks whether to keep each proposed value, allowing the default branch and tag pattern to
+    /// be changed.
+    pub fn ask(self) -> Result<ConfigScaffold, SheepError> {
+        let default_branch = prompt::ask("default branch", &self.default_branch)?;
+        let tag_pattern = prompt::ask("tag pattern", &self.tag_pattern)?;
+        let mut transforms = vec![];
+        for transform in self.transforms {
+            if prompt::confirm(&format!("update the version in {}?", transform.path))? {
+                transforms.push(transform);
+            }
+        }
+        Ok(ConfigScaffold { default_branch, tag_pattern, transforms })
+    }
+
+    /// Writes the config to `config_path`, or `sheepit.toml` in the repository when the repository
+    /// has no config, returning the path written.
+    pub fn write(&self,
+                 repo_path: &Path,
+                 config_path: Option<&Path>) -> Result<PathBuf, SheepError> {
+        let text = self.to_toml();
+        let config: Config = toml::from_str(&text)?;
+        let problems = config.pattern_problems();
+        if !problems.is_empty() {
+            return Err(SheepError::validation(&problems.join(", ")));
+        }
+        let config_path = match config_path {
+            Some(config_path) => config_path.to_path_buf(),
+            None => repo_path.join(CONFIG_NAME),
+        };
+        fs::write(&config_path, text)?;
+        Ok(config_path)
+    }
+
+    /// The config, with comments explaining each value.
+    pub fn to_toml(&self) -> String {
+        let enable_commit = !self.transforms.is_empty();
+        let mut lines = vec![
+            "# Generated by `sheepit init`. See the README for every option.".to_string(),
+            String::new(),
+            "[repository]".to_string(),
+            "# The branch releases are made from.".to_string(),
+            format!("default_branch = {}", quoted(&self.default_branch)),
+            "# When true a release branch is created before committing and tagging.".to_string(),
+            "enable_branch = false".to_string(),
+            "# When true transforms are applied and the changes are committed.".to_string(),
+            format!("enable_commit = {enable_commit}"),
+            "# When true the release is pushed to the remote.".to_string(),
+            "enable_push = true".to_string(),
+            format!("# The naming pattern for tags, where {} is the version.", token::VERSION),
+            format!("tag_pattern = {}", quoted(&self.tag_pattern)),
+            String::new(),
+            "# Each transform finds and replaces a single string in a file.".to_string(),
+        ];
+        if self.transforms.is_empty() {
+            lines.extend([
+                "# [[transforms]]".to_string(),
+                "# path = 'VERSION'".to_string(),
+                format!("# replace = '{}'", token::VERSION),
+            ]);
+        }
+        for transform in &self.transforms {
+            lines.extend([
+                "[[transforms]]".to_string(),
+                format!("path = {}", quoted(&transform.path)),
+                format!("replace = {}", quoted(&transform.replace)),
+            ]);
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/// The most common style among tags which end in a version, such as `v{version}`.
+fn tag_pattern(tags: &[String]) -> String {
+    let version_tag = Regex::new(r"^(.*?)\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?$")
+        .expect("version tag regex should be valid");
+    let mut prefix_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in tags {
+        if let Some(prefix) = version_tag.captures(tag).and_then(|captures| captures.get(1)) {
+            *prefix_counts.entry(prefix.as_str()).or_default() += 1;
+        }
+    }
+    // Ties go to the first prefix alphabetically so the result doesn't depend on tag order
+    let best = prefix_counts.into_iter().max_by(|(left, left_count), (right, right_count)| {
+        left_count.cmp(right_count).then(right.cmp(left))
+    });
+    match best {
+        Some((prefix, _)) => format!("{prefix}{}", token::VERSION),
+        None => default_tag_pattern(),
+    }
+}
+
+/// A transform updating the manifest's version, provided it's written the way the transform
+/// expects to find it.
+fn version_transform(manifest: Manifest, text: &str) -> Option<TransformConfig> {
+    let (version, replace) = match manifest {
+        Manifest::Cargo => {
+            let table = toml::from_str::<Table>(text).ok()?;
+            let version = nested_str(&table, &["package", "version"])?;
+            (version, format!("version = \"{}\"", token::VERSION))
+        }
+        Manifest::Pyproject => {
+            let table = toml::from_str::<Table>(text).ok()?;
+            let version = nested_str(&table, &["project", "version"])
+                .or_else(|| nested_str(&table, &["tool", "poetry", "version"]))?;
+            (version, format!("version = \"{}\"", token::VERSION))
+        }
+        Manifest::Npm => {
+            let value = serde_json::from_str::<serde_json::Value>(text).ok()?;
+            let version = value.get("version")?.as_str()?.to_string();
+            (version, format!("\"version\": \"{}\"", token::VERSION))
+        }
+    };
+    if !text.contains(&replace.replace(token::VERSION, &version)) {
+        return None;
+    }
+    Some(TransformConfig {
+        path: manifest.file_name().to_string(),
+        find: None,
+        replace,
+    })
+}
+
+fn nested_str(table: &Table, keys: &[&str]) -> Option<String> {
+    let (last, tables) = keys.split_last()?;
+    let table = tables
+        .iter()
+        .try_fold(table, |table, key| table.get(*key)?.as_table())?;
+    table.get(*last)?.as_str().map(String::from)
+}
+
+fn quoted(text: &str) -> String {
+    Value::String(text.to_string()).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::manifest::Manifest;
+    use crate::config::scaffold::{tag_pattern, version_transform, ConfigScaffold};
+    use crate::config::{Config, TransformConfig};
+    use crate::repo::testing;
+    use std::fs;
+
+    #[test]
+    fn detect() {
+        let (temp_dir, repo) = testing::init_repo();
+        repo.set_head("refs/heads/trunk").unwrap();
+        let cargo = "[package]\nname = \"sheep\"\nversion = \"1.2.0\"\n";
+        testing::write_file(&repo, "Cargo.toml", cargo);
+        testing::write_file(&repo, "package.json", "{ \"version\": \"1.2.0\" }");
+        testing::commit(&repo, "first");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.tag_lightweight("v1.2.0", head.as_object(), false).unwrap();
+
+        let scaffold = ConfigScaffold::detect(&repo, temp_dir.path()).expect("failed to detect");
+        let expected = ConfigScaffold {
+            default_branch: "trunk".to_string(),
+            tag_pattern: "v{version}".to_string(),
+            transforms: vec![
+                transform("Cargo.toml", "version = \"{version}\""),
+                transform("package.json", "\"version\": \"{version}\""),
+            ],
+        };
+        assert_eq!(expected, scaffold)
+    }
+
+    #[test]
+    fn tag_pattern_most_common_prefix() {
+        let mixed_tags = tags(&["v1.0.0", "v1.1.0", "1.2.0", "v2.0.0-beta.1", "latest"]);
+        assert_eq!("v{version}", tag_pattern(&mixed_tags));
+        assert_eq!("app-{version}", tag_pattern(&tags(&["app-1.0.0", "web-1.0.0"])));
+        assert_eq!("{version}", tag_pattern(&[]))
+    }
+
+    #[test]
+    fn version_transform_pyproject() {
+        let text = "[tool.poetry]\nname = \"sheep\"\nversion = \"0.3.0\"\n";
+        let expected = transform("pyproject.toml", "version = \"{version}\"");
+        assert_eq!(Some(expected), version_transform(Manifest::Pyproject, text))
+    }
+
+    #[test]
+    fn version_transform_unexpected_format() {
+        let text = "[package]\nname = \"sheep\"\nversion='1.0.0'\n";
+        assert_eq!(None, version_transform(Manifest::Cargo, text));
+        let text = "[workspace]\nmembers = []\n";
+        assert_eq!(None, version_transform(Manifest::Cargo, text))
+    }
+
+    #[test]
+    fn to_toml_parses() {
+        let scaffold = ConfigScaffold {
+            default_branch: "main".to_string(),
+            tag_pattern: "v{version}".to_string(),
+            transforms: vec![transform("Cargo.toml", "version = \"{version}\"")],
+        };
+        let config: Config = toml::from_str(&scaffold.to_toml()).expect("failed to parse");
+        assert!(config.repository.enable_commit);
+        assert_eq!("v{version}", config.repository.tag_pattern);
+        assert_eq!(scaffold.transforms, config.transforms)
+    }
+
+    #[test]
+    fn write_rejects_invalid_pattern() {
+        let (temp_dir, _repo) = testing::init_repo();
+        let scaffold = ConfigScaffold {
+            default_branch: "main".to_string(),
+            tag_pattern: "release".to_string(),
+            transforms: vec![],
+        };
+        scaffold.write(temp_dir.path(), None).expect_err("should fail");
+        assert!(fs::metadata(temp_dir.path().join("sheepit.toml")).is_err())
+    }
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn transform(path: &str, replace: &str) -> TransformConfig {
+        TransformConfig {
+            path: path.to_string(),
+            find: None,
+            replace: replace.to_string(),
+        }
+    }
+}
@@ -1,46 +1,230 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::io;
 
-#[derive(Debug, PartialEq)]
-pub struct SheepError {
-    message: String
+type Source = Box<dyn Error + Send + Sync>;
+
+/// An error from sheepit, categorised so callers can tell what went wrong. The underlying error,
+/// if there is one, is available through [`Error::source`].
+#[derive(Debug)]
+pub enum SheepError {
+    /// Authenticating with a remote failed.
+    Auth { message: String, source: Option<Source> },
+    /// The config is invalid or couldn't be read.
+    Config { message: String, source: Option<Source> },
+    /// A git operation failed.
+    Git { message: String, source: Option<Source> },
+    /// Reading or writing a file failed.
+    Io { message: String, source: Option<Source> },
+    /// A script failed or couldn't be run.
+    Script { message: String, source: Option<Source> },
+    /// One or more subprojects failed to release.
+    Subproject { message: String, source: Option<Source> },
+    /// A transform couldn't be applied.
+    Transform { message: String, source: Option<Source> },
+    /// The repository or requested version doesn't allow the release.
+    Validation { message: String, source: Option<Source> },
 }
 
 impl SheepError {
-    pub fn new(message: &str) -> Self {
-        SheepError {
-            message: format!("😱 {message}")
+    pub fn auth(message: &str) -> Self {
+        SheepError::Auth { message: message.to_string(), source: None }
+    }
+
+    pub fn config(message: &str) -> Self {
+        SheepError::Config { message: message.to_string(), source: None }
+    }
+
+    pub fn git(message: &str) -> Self {
+        SheepError::Git { message: message.to_string(), source: None }
+    }
+
+    pub fn io(message: &str) -> Self {
+        SheepError::Io { message: message.to_string(), source: None }
+    }
+
+    pub fn script(message: &str) -> Self {
+        SheepError::Script { message: message.to_string(), source: None }
+    }
+
+    pub fn subproject(message: &str) -> Self {
+        SheepError::Subproject { message: message.to_string(), source: None }
+    }
+
+    pub fn transform(message: &str) -> Self {
+        SheepError::Transform { message: message.to_string(), source: None }
+    }
+
+    pub fn validation(message: &str) -> Self {
+        SheepError::Validation { message: message.to_string(), source: None }
+    }
+
+    /// Records the error which caused this one.
+    pub fn with_source<E: Into<Source>>(mut self, error: E) -> Self {
+        *self.source_mut() = Some(error.into());
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        self.parts().0
+    }
+
+    /// The message followed by the message of each error in the source chain.
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = self.source();
+        while let Some(error) = source {
+            report.push_str(&format!(": {error}"));
+            source = error.source();
+        }
+        report
+    }
+
+    /// The process exit code for the error's category.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            SheepError::Validation { .. } => 2,
+            SheepError::Config { .. } => 3,
+            SheepError::Git { .. } => 4,
+            SheepError::Auth { .. } => 5,
+            SheepError::Io { .. } => 6,
+            SheepError::Script { .. } => 7,
+            SheepError::Transform { .. } => 8,
+            SheepError::Subproject { .. } => 9,
+        }
+    }
+
+    fn parts(&self) -> (&str, &Option<Source>) {
+        match self {
+            SheepError::Auth { message, source }
+            | SheepError::Config { message, source }
+            | SheepError::Git { message, source }
+            | SheepError::Io { message, source }
+            | SheepError::Script { message, source }
+            | SheepError::Subproject { message, source }
+            | SheepError::Transform { message, source }
+            | SheepError::Validation { message, source } => (message, source),
+        }
+    }
+
+    fn source_mut(&mut self) -> &mut Option<Source> {
+        match self {
+            SheepError::Auth { source, .. }
+            | SheepError::Config { source, .. }
+            | SheepError::Git { source, .. }
+            | SheepError::Io { source, .. }
+            | SheepError::Script { source, .. }
+            | SheepError::Subproject { source, .. }
+            | SheepError::Transform { source, .. }
+            | SheepError::Validation { source, .. } => source,
         }
     }
 }
 
+impl Display for SheepError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl Error for SheepError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.parts().1.as_ref().map(|source| source.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+/// Errors are equal when they have the same category and message.
+impl PartialEq for SheepError {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.message() == other.message()
+    }
+}
+
 impl From<git2::Error> for SheepError {
     fn from(value: git2::Error) -> Self {
-        Self {
-            message: format!("😱 git error: {value}")
-        }
+        let is_auth = value.code() == git2::ErrorCode::Auth
+            || value.class() == git2::ErrorClass::Ssh;
+        let error = if is_auth {
+            SheepError::auth("git authentication failed")
+        } else {
+            SheepError::git("git error")
+        };
+        error.with_source(value)
+    }
+}
+
+impl From<glob::PatternError> for SheepError {
+    fn from(value: glob::PatternError) -> Self {
+        SheepError::config("glob pattern error").with_source(value)
     }
 }
 
 impl From<io::Error> for SheepError {
     fn from(value: io::Error) -> Self {
-        Self {
-            message: format!("😱 io error: {value}")
-        }
+        SheepError::io("io error").with_source(value)
     }
 }
 
 impl From<parse_git_url::FromStrError> for SheepError {
     fn from(value: parse_git_url::FromStrError) -> Self {
-        Self {
-            message: format!("😱 git url parse error: {value}")
-        }
+        SheepError::config("git url parse error").with_source(value)
+    }
+}
+
+impl From<regex::Error> for SheepError {
+    fn from(value: regex::Error) -> Self {
+        SheepError::config("regex error").with_source(value)
+    }
+}
+
+impl From<semver::Error> for SheepError {
+    fn from(value: semver::Error) -> Self {
+        SheepError::config("version requirement error").with_source(value)
+    }
+}
+
+impl From<serde_json::Error> for SheepError {
+    fn from(value: serde_json::Error) -> Self {
+        SheepError::config("json parse error").with_source(value)
     }
 }
 
 impl From<toml::de::Error> for SheepError {
     fn from(value: toml::de::Error) -> Self {
-        Self {
-            message: format!("😱 config parse error: {value}")
-        }
+        SheepError::config("config parse error").with_source(value)
+    }
+}
+
+impl From<toml::ser::Error> for SheepError {
+    fn from(value: toml::ser::Error) -> Self {
+        SheepError::config("config serialize error").with_source(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::SheepError;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn report_includes_sources() {
+        let io_error = io::Error::new(io::ErrorKind::NotFound, "missing file");
+        let error = SheepError::config("failed to read config").with_source(io_error);
+        assert_eq!("failed to read config: missing file", error.report());
+        assert!(error.source().is_some())
+    }
+
+    #[test]
+    fn from_io_error() {
+        let error = SheepError::from(io::Error::other("disk full"));
+        assert_eq!(SheepError::io("io error"), error);
+        assert_eq!(6, error.exit_code())
+    }
+
+    #[test]
+    fn equality_uses_category() {
+        assert_ne!(SheepError::config("oops"), SheepError::script("oops"))
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,18 @@
-use crate::project::Project;
+use crate::config::{Config, ConfigScaffold, Manifest, UnknownKeys};
+use crate::project::{Project, SubprojectOptions};
+use crate::repo::open::GitOpener;
+use semver::Version;
 use std::path::{Path, PathBuf};
 
 pub use crate::error::SheepError;
 pub use crate::project::operation::{BumpMode, Operation};
+pub use crate::project::options::{InitOptions, UndoOptions, UpdateOptions};
 
 mod config;
 mod error;
 mod file;
 mod project;
+mod prompt;
 mod repo;
 mod script;
 mod token;
@@ -17,16 +22,171 @@ mod version;
 pub fn project_update<P: AsRef<Path>>(
     operation: Operation,
     path: P,
-    dry_run: bool,
+    options: &UpdateOptions,
 ) -> Result<(), SheepError> {
     let expanded_path = expand_path(path);
-    let project = if dry_run {
-        Project::new_dry_run_project(&expanded_path)?
+    let mut project = open_project(&expanded_path, options)?;
+    if let Some(package) = &options.package {
+        project = project.with_package(package)?;
+    }
+    if !options.resume {
+        project.clear_transaction_log()?;
+    }
+
+    project.update(operation)?;
+    Ok(())
+}
+
+/// Releases several packages from the config's packages, one after another. When `changed_only`
+/// is true only packages with changes since their latest tag are released. Packages depending
+/// on a released package receive a patch release after it, with their dependency transforms
+/// applied.
+pub fn project_release<P: AsRef<Path>>(
+    operation: Operation,
+    path: P,
+    options: &UpdateOptions,
+    changed_only: bool,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let project = Project::new_local_project(&expanded_path, options.config_path.as_deref())?;
+    let package_names =
+        project.release_package_names(options.package.as_deref(), changed_only)?;
+    if package_names.is_empty() {
+        println!("🐑 no packages need releasing");
+        return Ok(());
+    }
+    let plan = project.release_plan(&package_names)?;
+    println!("{plan}");
+    project.release(&plan, operation, options.dry_run, options.resume, |package| {
+        open_project(&expanded_path, options)?.with_package(package)
+    })
+}
+
+pub fn project_changed<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<&Path>,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let project = Project::new_local_project(&expanded_path, config_path)?;
+    for changes in project.package_changes()? {
+        println!("{changes}");
+    }
+    Ok(())
+}
+
+/// Undoes the branches, commits and tags created by the last run. Refs which were pushed are only
+/// deleted from the remote when `remote` is true.
+pub fn project_rollback<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<&Path>,
+    remote: bool,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let project = Project::new_local_project(&expanded_path, config_path)?;
+    project.rollback(remote)
+}
+
+/// Retracts the release of `version`, deleting its tag and, depending on the options, its release
+/// branch and release commit.
+pub fn project_undo<P: AsRef<Path>>(
+    version: &Version,
+    path: P,
+    options: &UndoOptions,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let mut project = Project::new_local_project(&expanded_path, options.config_path.as_deref())?;
+    if let Some(package) = &options.package {
+        project = project.with_package(package)?;
+    }
+    project.undo(version, options)
+}
+
+/// Validates the config, strictly rejecting unknown keys, and checks it against the repository.
+/// Each problem is printed and the check fails if any were found.
+pub fn project_check<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<&Path>,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let config = Config::open_strict(&expanded_path, config_path)?;
+    let project = Project::new_local_project_with_config(&expanded_path, config)?;
+    let problems = project.check()?;
+    if problems.is_empty() {
+        println!("✅ config is valid");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("❌ {problem}");
+    }
+    let message = format!("config has {} problem(s)", problems.len());
+    Err(SheepError::validation(&message))
+}
+
+/// Writes a `sheepit.toml` proposed from the repository's manifests, tags and default branch.
+/// Unless `yes` is set each proposed value is confirmed first. With `force` an existing config
+/// file is replaced in place, while a config embedded in a manifest is never replaced.
+pub fn project_init<P: AsRef<Path>>(path: P, options: &InitOptions) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let existing_path = Config::find(&expanded_path)?;
+    if let Some(config_path) = &existing_path {
+        let path = config_path.to_string_lossy();
+        if Manifest::from_path(config_path).is_some() {
+            let message = format!("a config is already embedded in {path}, remove it to run init");
+            return Err(SheepError::validation(&message));
+        }
+        if !options.force {
+            let message = format!("a config already exists at {path}, use --force to replace it");
+            return Err(SheepError::validation(&message));
+        }
+    }
+    let repo = GitOpener::new().open(&expanded_path)?;
+    let mut scaffold = ConfigScaffold::detect(&repo, &expanded_path)?;
+    for transform in &scaffold.transforms {
+        println!("🔍 found a version in {}", transform.path);
+    }
+    if !options.yes {
+        scaffold = scaffold.ask()?;
+    }
+    let config_path = scaffold.write(&expanded_path, existing_path.as_deref())?;
+    println!("📝 wrote {}", config_path.to_string_lossy());
+    Ok(())
+}
+
+pub fn project_tags<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<&Path>,
+    package: Option<&str>,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let mut project = Project::new_local_project(&expanded_path, config_path)?;
+    if let Some(package) = package {
+        project = project.with_package(package)?;
+    }
+    project.print_tags()
+}
+
+/// Prints every value of the merged config along with the file or environment variable it came
+/// from.
+pub fn project_config_show<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<&Path>,
+) -> Result<(), SheepError> {
+    let expanded_path = expand_path(path);
+    let layers = Config::layers(&expanded_path, config_path, UnknownKeys::Warn)?;
+    for (key, value, source) in layers.values()? {
+        println!("{key} = {value}  # {source}");
+    }
+    Ok(())
+}
+
+fn open_project<P: AsRef<Path>>(path: P, options: &UpdateOptions) -> Result<Project, SheepError> {
+    let project = if options.dry_run {
+        Project::new_dry_run_project(path, options.config_path.as_deref())?
     } else {
-        Project::new_local_project(&expanded_path)?
+        Project::new_local_project(path, options.config_path.as_deref())?
     };
-
-    project.update(operation)
+    let subproject_options = SubprojectOptions::new(options.jobs, options.max_depth, options.resume);
+    Ok(project.with_subproject_options(subproject_options))
 }
 
 fn expand_path<P: AsRef<Path>>(path: P) -> PathBuf {
@@ -52,4 +212,5 @@ mod test {
 
         assert_eq!(expected, expanded_path.to_string_lossy().to_string())
     }
+
 }
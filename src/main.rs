@@ -1,6 +1,13 @@
 use clap::{CommandFactory, Parser};
-use sheepit::{BumpMode, Operation, project_update, SheepError};
-use crate::cli::{MajorBumpArgs, MinorBumpArgs, PatchBumpArgs};
+use std::process::ExitCode;
+use sheepit::{
+    BumpMode, Operation, project_changed, project_check, project_config_show, project_init,
+    project_release, project_rollback, project_tags, project_undo, project_update, SheepError,
+};
+use crate::cli::{
+    ChangedArgs, CheckArgs, ConfigCommand, InitArgs, MajorBumpArgs, MinorBumpArgs, PatchBumpArgs,
+    ReleaseArgs, RollbackArgs, TagsArgs, UndoArgs,
+};
 
 mod cli;
 
@@ -10,6 +17,15 @@ pub enum SheepitCLI {
     Major(MajorBumpArgs),
     Minor(MinorBumpArgs),
     Patch(PatchBumpArgs),
+    Release(ReleaseArgs),
+    Changed(ChangedArgs),
+    Check(CheckArgs),
+    #[command(about = "Inspects the config", subcommand)]
+    Config(ConfigCommand),
+    Init(InitArgs),
+    Rollback(RollbackArgs),
+    Tags(TagsArgs),
+    Undo(UndoArgs),
     #[command(about = "prints out completions for the provided shell")]
     Completions {
         #[arg(value_enum)]
@@ -17,24 +33,78 @@ pub enum SheepitCLI {
     },
 }
 
-fn main() -> Result<(), SheepError> {
-    let command = SheepitCLI::parse();
+/// The exit code for invalid command line arguments. Clap's own code would be mistaken for a
+/// validation error.
+const USAGE_EXIT_CODE: u8 = 1;
+
+fn main() -> ExitCode {
+    let command = match SheepitCLI::try_parse() {
+        Ok(command) => command,
+        Err(error) => {
+            // Help and version are reported as errors too, but print to stdout and succeed
+            let _ = error.print();
+            return if error.use_stderr() {
+                ExitCode::from(USAGE_EXIT_CODE)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    match run(command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("😱 {}", error.report());
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+fn run(command: SheepitCLI) -> Result<(), SheepError> {
     match command {
         SheepitCLI::Major(args) => {
-            let operation = Operation::BumpVersion(BumpMode::Major);
-            project_update(operation, args.repo_path, args.dry_run)?
+            let operation = bump_operation(BumpMode::Major, args.update.initial);
+            project_update(operation, args.repo_path, &args.update.options())?
         }
         SheepitCLI::Minor(args) => {
-            let operation = Operation::BumpVersion(BumpMode::Minor);
-            project_update(operation, args.repo_path, args.dry_run)?
+            let operation = bump_operation(BumpMode::Minor, args.update.initial);
+            project_update(operation, args.repo_path, &args.update.options())?
         }
         SheepitCLI::Patch(args) => {
-            let operation = Operation::BumpVersion(BumpMode::Patch);
-            project_update(operation, args.repo_path, args.dry_run)?
+            let operation = bump_operation(BumpMode::Patch, args.update.initial);
+            project_update(operation, args.repo_path, &args.update.options())?
+        }
+        SheepitCLI::Release(args) => {
+            let operation = bump_operation(args.bump.mode(), args.update.initial);
+            project_release(operation, args.repo_path, &args.update.options(), args.changed)?
         }
+        SheepitCLI::Changed(args) => {
+            project_changed(args.repo_path, args.config.config_path.as_deref())?
+        }
+        SheepitCLI::Check(args) => project_check(args.repo_path, args.config.config_path.as_deref())?,
+        SheepitCLI::Config(ConfigCommand::Show(args)) => {
+            project_config_show(args.repo_path, args.config.config_path.as_deref())?
+        }
+        SheepitCLI::Init(args) => project_init(&args.repo_path, &args.options())?,
+        SheepitCLI::Rollback(args) => {
+            let config_path = args.config.config_path.as_deref();
+            project_rollback(args.repo_path, config_path, args.remote)?
+        }
+        SheepitCLI::Tags(args) => {
+            let config_path = args.config.config_path.as_deref();
+            project_tags(args.repo_path, config_path, args.package.as_deref())?
+        }
+        SheepitCLI::Undo(args) => project_undo(&args.version, &args.repo_path, &args.options())?,
         SheepitCLI::Completions { shell } => {
             shell.generate(&mut SheepitCLI::command(), &mut std::io::stdout());
         }
     };
     Ok(())
 }
+
+fn bump_operation(bump_mode: BumpMode, initial: bool) -> Operation {
+    if initial {
+        Operation::Initial(bump_mode)
+    } else {
+        Operation::BumpVersion(bump_mode)
+    }
+}
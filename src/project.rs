@@ -1,27 +1,50 @@
+pub mod changes;
+mod graph;
 pub mod operation;
+pub mod options;
 mod project_version;
+pub mod release;
+mod run_state;
+mod staging;
 mod strings;
-mod temp;
+mod subproject;
+pub(crate) mod temp;
+mod transaction;
 
-use crate::config::{Config, RepoConfig};
+use crate::config::{Config, PackageConfig, RepoConfig, TransformConfig};
 use crate::error::SheepError;
-use crate::project::operation::Operation;
+use crate::prompt;
+use crate::project::operation::{BumpMode, Operation};
+use crate::project::options::UndoOptions;
 use crate::repo::clone::GitCloner;
 use crate::repo::open::GitOpener;
 use crate::repo::path;
 use crate::repo::remote::GitRemotes;
-use git2::Repository;
+use crate::repo::status::GitStatuses;
+use git2::{Oid, Repository};
 use mockall_double::double;
-use std::path::Path;
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 #[double]
 use crate::project::project_version::ProjectVersion;
+use crate::project::changes::PackageChanges;
+use crate::project::graph::PackageGraph;
+use crate::project::release::{DependencyUpdate, ReleasePlan, ReleaseStep};
+use crate::project::run_state::{PlanState, RunState};
+use crate::project::staging::CommitStaging;
 use crate::project::strings::ProjectStrings;
+use crate::project::transaction::{Mutation, TransactionLog};
+pub use crate::project::subproject::SubprojectOptions;
 use crate::repo::branch::GitBranches;
 use crate::repo::commit::GitCommits;
+use crate::repo::diff::GitDiffs;
 use crate::repo::tag::GitTags;
 use crate::script::ScriptRunner;
 use crate::transform::project_transform::ProjectTransformer;
+use crate::version::constraint::VersionConstraint;
+use crate::version::tag_match::TagMatch;
 use crate::version::update::VersionUpdate;
 
 pub struct Project {
@@ -29,18 +52,36 @@ pub struct Project {
     repo: Repository,
     transformer: ProjectTransformer,
     is_dry_run_project: bool,
+    package: Option<PackageConfig>,
+    dependency_updates: Vec<DependencyUpdate>,
+    subproject_options: SubprojectOptions,
 }
 
 impl Project {
-    pub fn new_local_project<P: AsRef<Path>>(path: P) -> Result<Project, SheepError> {
+    /// Opens the repository at `path`. `config_path` replaces the config found in the repository.
+    pub fn new_local_project<P: AsRef<Path>>(
+        path: P,
+        config_path: Option<&Path>,
+    ) -> Result<Project, SheepError> {
+        let config = Config::open(&path, config_path)?;
+        Project::new_local_project_with_config(path, config)
+    }
+
+    /// Opens the repository at `path`, releasing it with a config which has already been opened.
+    pub fn new_local_project_with_config<P: AsRef<Path>>(
+        path: P,
+        config: Config,
+    ) -> Result<Project, SheepError> {
         let repo = GitOpener::new().open(&path)?;
-        let config = Config::open(&path)?;
         let transformer = ProjectTransformer::new(path);
         let project = Project {
             config,
             repo,
             transformer,
             is_dry_run_project: false,
+            package: None,
+            dependency_updates: vec![],
+            subproject_options: SubprojectOptions::default(),
         };
         Ok(project)
     }
@@ -49,111 +90,796 @@ impl Project {
         url: &str,
         directory: P,
         is_dry_run_project: bool,
+        ssh_key_path: Option<&str>,
     ) -> Result<Project, SheepError> {
         let repo_path = path::repo_path(url, directory)?;
-        let repo = GitCloner::new().clone(url, &repo_path)?;
-        let config = Config::open(&repo_path)?;
+        let repo = GitCloner::new()
+            .with_ssh_key_path(ssh_key_path)
+            .clone(url, &repo_path)?;
+        let config = Config::open(&repo_path, None)?;
         let transformer = ProjectTransformer::new(&repo_path);
         let project = Project {
             config,
             repo,
             transformer,
             is_dry_run_project,
+            package: None,
+            dependency_updates: vec![],
+            subproject_options: SubprojectOptions::default(),
         };
         Ok(project)
     }
 
-    pub fn new_dry_run_project<P: AsRef<Path>>(path: P) -> Result<Project, SheepError> {
-        let remotes = GitRemotes::new();
-        let local_project = Project::new_local_project(path)?;
-        let remote_url = remotes.remote_url(&local_project.repo, "origin")?;
+    pub fn new_dry_run_project<P: AsRef<Path>>(
+        path: P,
+        config_path: Option<&Path>,
+    ) -> Result<Project, SheepError> {
+        let local_project = Project::new_local_project(path, config_path)?;
+        let repo_config = &local_project.config.repository;
+        let remote_url = local_project
+            .remotes()
+            .remote_url(&local_project.repo, &repo_config.remote_name)?;
         let directory = temp::directory()?;
 
-        let remote_project = Project::new_remote_project(&remote_url, directory, false)?;
+        let ssh_key_path = repo_config.ssh_key_path.as_deref();
+        let remote_project =
+            Project::new_remote_project(&remote_url, directory, false, ssh_key_path)?;
         let dry_run_project = Project {
             config: local_project.config,
             is_dry_run_project: true,
             repo: remote_project.repo,
             transformer: remote_project.transformer,
+            package: None,
+            dependency_updates: vec![],
+            subproject_options: SubprojectOptions::default(),
         };
         Ok(dry_run_project)
     }
 
-    pub fn update(&self, operation: Operation) -> Result<(), SheepError> {
+    /// Narrows the project to a single package. Versions are computed from the package's tags
+    /// and only changes within the package are committed.
+    pub fn with_package(self, package_name: &str) -> Result<Project, SheepError> {
+        let package = self.config.package(package_name)?.clone();
+        let config = self.config.for_package(&package);
+        let project = Project {
+            config,
+            package: Some(package),
+            ..self
+        };
+        Ok(project)
+    }
+
+    /// Provides the versions of dependencies released earlier in a release plan. Their
+    /// transforms are applied along with the package's own transforms.
+    pub fn with_dependency_updates(self, dependency_updates: Vec<DependencyUpdate>) -> Project {
+        Project {
+            dependency_updates,
+            ..self
+        }
+    }
+
+    /// The dependencies of the step's package which were released earlier in the plan, with the
+    /// transforms updating the package's requirement on each. Dependencies inferred from
+    /// manifests have no configured transforms, so theirs are found in the package's manifests.
+    pub fn dependency_updates(
+        &self,
+        step: &ReleaseStep,
+        released: &BTreeMap<String, VersionUpdate>,
+    ) -> Result<Vec<DependencyUpdate>, SheepError> {
+        let package = self.config.package(&step.package)?;
+        let mut dependency_updates = vec![];
+        for name in &step.released_dependencies {
+            let Some(version_update) = released.get(name) else {
+                continue;
+            };
+            let configured = package.dependencies.iter().find(|dependency| &dependency.name == name);
+            let transforms = match configured {
+                Some(dependency) => dependency
+                    .transforms
+                    .iter()
+                    .map(|transform| TransformConfig {
+                        path: package.relative_path(&transform.path),
+                        ..transform.clone()
+                    })
+                    .collect(),
+                None => graph::requirement_transforms(
+                    self.repo_path(),
+                    package,
+                    self.config.package(name)?,
+                    &version_update.current_version,
+                )?,
+            };
+            dependency_updates.push(DependencyUpdate {
+                name: name.clone(),
+                transforms,
+                version_update: version_update.clone(),
+            });
+        }
+        Ok(dependency_updates)
+    }
+
+    /// Controls how subprojects are released when the project is updated.
+    pub fn with_subproject_options(self, subproject_options: SubprojectOptions) -> Project {
+        Project {
+            subproject_options,
+            ..self
+        }
+    }
+
+    /// Plans the release of the provided packages along with every package depending on them.
+    pub fn release_plan(&self, package_names: &[String]) -> Result<ReleasePlan, SheepError> {
+        let packages = &self.config.packages;
+        let mut graph = PackageGraph::from_config(packages)?;
+        if self.config.repository.infer_dependencies {
+            graph.infer_from_manifests(self.repo_path(), packages);
+        }
+        let plan = ReleasePlan::new(&graph, package_names)?;
+        if self.config.repository.enable_branch {
+            self.check_branch_patterns(&plan)?;
+        }
+        Ok(plan)
+    }
+
+    /// Each package in a plan is released on its own branch, so packages sharing a branch
+    /// pattern would collide whenever they're released at the same version.
+    fn check_branch_patterns(&self, plan: &ReleasePlan) -> Result<(), SheepError> {
+        let mut packages_by_pattern: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for step in &plan.steps {
+            let package = self.config.package(&step.package)?;
+            let branch_pattern = package
+                .branch_pattern
+                .clone()
+                .unwrap_or_else(|| self.config.repository.branch_pattern.clone());
+            packages_by_pattern.entry(branch_pattern).or_default().push(&step.package);
+        }
+        match packages_by_pattern.iter().find(|(_, packages)| packages.len() > 1) {
+            Some((branch_pattern, packages)) => {
+                let message = format!(
+                    "packages {} share branch_pattern '{branch_pattern}', set a branch_pattern \
+                    for each package",
+                    packages.join(", ")
+                );
+                Err(SheepError::config(&message))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Checks out the default branch when releases are made on a release branch, so the next
+    /// package in a plan isn't released from the previous package's release branch.
+    /// Releases each step of the plan, one after another, with the package project provided by
+    /// `open_package`. Released packages are recorded so that a resumed release skips them.
+    pub fn release<F>(
+        &self,
+        plan: &ReleasePlan,
+        operation: Operation,
+        dry_run: bool,
+        resume: bool,
+        open_package: F,
+    ) -> Result<(), SheepError>
+    where
+        F: Fn(&str) -> Result<Project, SheepError>,
+    {
+        // Dry runs release from a copy, so the local repository's logs are left alone
+        let plan_state_path = PlanState::path(self.repo.path());
+        let mut plan_state = PlanState::default();
+        if resume {
+            plan_state = PlanState::load(&plan_state_path)?.unwrap_or_default();
+        } else if !dry_run {
+            self.clear_transaction_log()?;
+            PlanState::remove(&plan_state_path)?;
+        }
+
+        let mut released = BTreeMap::new();
+        for (index, step) in plan.steps.iter().enumerate() {
+            if let Some(version_update) = plan_state.released_version(&step.package) {
+                println!("⏭  skipping {}, it was already released", step.package);
+                released.insert(step.package.clone(), version_update);
+                continue;
+            }
+            let step_operation = if step.requested {
+                operation.clone()
+            } else {
+                Operation::BumpVersion(BumpMode::Patch)
+            };
+            // Dry runs release each package from a fresh copy which is already on the default branch
+            if index > 0 && !dry_run {
+                self.checkout_default_branch()?;
+            }
+            let dependency_updates = self.dependency_updates(step, &released)?;
+            let package_project =
+                open_package(&step.package)?.with_dependency_updates(dependency_updates);
+            println!("------------");
+            let version_update = package_project.update(step_operation)?;
+            if !dry_run {
+                plan_state.set_released(&step.package, &version_update);
+                plan_state.save(&plan_state_path)?;
+            }
+            released.insert(step.package.clone(), version_update);
+        }
+        PlanState::remove(&plan_state_path)
+    }
+
+    pub fn checkout_default_branch(&self) -> Result<(), SheepError> {
         let repo_config = &self.config.repository;
-        let project_version = ProjectVersion::new(&self);
+        if !repo_config.enable_branch {
+            return Ok(());
+        }
+        let default_branch = &repo_config.default_branch;
+        println!("🌲 checking out {default_branch}");
+        GitBranches::new().checkout_branch(&self.repo, default_branch)?;
+        Ok(())
+    }
+
+    pub fn update(&self, operation: Operation) -> Result<VersionUpdate, SheepError> {
+        self.check_subproject_cycles()?;
+        let run_state = if self.subproject_options.resume {
+            RunState::load(self.run_state_path())?
+        } else {
+            None
+        };
+        let version_update = match &run_state {
+            Some(run_state) => {
+                let version_update = run_state.version_update();
+                println!(
+                    "⏯  resuming release of {}, project was already sheep'd",
+                    version_update.next_version
+                );
+                version_update
+            }
+            None => self.update_project(operation.clone())?,
+        };
+
+        // Process subprojects if there are any
+        if let Err(error) = self.update_subprojects(&version_update, run_state) {
+            let project_strings = ProjectStrings::new(&self.config, &version_update);
+            let script_runner = self.script_runner(&operation, &version_update, &project_strings);
+            self.run_on_failure(&script_runner);
+            return Err(error);
+        }
+
+        Ok(version_update)
+    }
+
+    fn update_project(&self, operation: Operation) -> Result<VersionUpdate, SheepError> {
+        if let Some(package) = &self.package {
+            println!("📦 releasing package {}", package.name);
+        }
+        let repo_config = &self.config.repository;
+        let version_constraint = self.version_constraint()?;
+        let project_version = ProjectVersion::new(&self, version_constraint.as_ref())?;
         let version_update = operation.version_update(&project_version);
         let project_strings = ProjectStrings::new(&self.config, &version_update);
 
-        self.update_repo(repo_config, &project_strings, &version_update)?;
+        let scripts = &self.config.scripts;
+        let script_runner = self.script_runner(&operation, &version_update, &project_strings);
+        let mut transaction = TransactionLog::open(self.repo.path())?;
+        let first_mutation = transaction.len();
+        // Validate before any scripts run or refs are created so a refused release leaves the repo
+        // untouched, while still running on_failure
+        let result = match &version_constraint {
+            Some(constraint) => constraint.validate(&version_update.next_version),
+            None => Ok(()),
+        };
+        let result = result
+            .and_then(|_| script_runner.run(scripts.before_all.as_ref(), "before_all"))
+            .and_then(|_| {
+                script_runner.run(scripts.after_version_computed.as_ref(), "after_version_computed")
+            })
+            .and_then(|_| {
+                self.update_repo(repo_config, &project_strings, &script_runner, &mut transaction)
+            });
+        if let Err(error) = result {
+            let pushed = transaction.has_pushed_since(first_mutation);
+            if pushed && !repo_config.rollback_remote {
+                // Rolling back locally would leave the pushed refs behind on the remote
+                println!(
+                    "⚠️  release {} was already pushed, run sheepit rollback --remote to undo it",
+                    version_update.next_version
+                );
+            } else if repo_config.rollback_on_failure && transaction.len() > first_mutation {
+                println!("⏪ rolling back release {}", version_update.next_version);
+                let remotes = self.remotes();
+                let remotes = repo_config.rollback_remote.then_some(&remotes);
+                if let Err(rollback_error) = transaction.roll_back(&self.repo, first_mutation, remotes)
+                {
+                    println!(
+                        "⚠️  rollback failed, run sheepit rollback to retry: {}",
+                        rollback_error.report()
+                    );
+                }
+            }
+            self.run_on_failure(&script_runner);
+            return Err(error);
+        }
 
         // Print out completion message, including dry run path if needed
         if self.is_dry_run_project {
-            let mut repo_path_buf = self.repo.path().to_path_buf();
-            repo_path_buf.pop(); // remove the .git path component
-            let repo_path = repo_path_buf.to_string_lossy().to_string();
+            let repo_path = self.repo_path().to_string_lossy().to_string();
             println!("🐑 dry run results may be found here: {repo_path}");
         } else {
             println!("🐑 project has been sheep'd");
         }
 
-        // Process subprojects if there are any
-        self.update_subprojects(&version_update)?;
+        Ok(version_update)
+    }
+
+    /// Undoes every change recorded during the last run. Pushed refs are deleted from the remote
+    /// when `remote` is true or the config enables `rollback_remote`.
+    pub fn rollback(&self, remote: bool) -> Result<(), SheepError> {
+        let mut transaction = TransactionLog::open(self.repo.path())?;
+        if transaction.is_empty() {
+            println!("🐑 nothing to roll back");
+            return Ok(());
+        }
+        println!("⏪ rolling back the last run");
+        let remotes = self.remotes();
+        let remote = remote || self.config.repository.rollback_remote;
+        transaction.roll_back(&self.repo, 0, remote.then_some(&remotes))?;
+        // Pushed refs left in place stay in the log for a later rollback with --remote
+        if transaction.is_empty() {
+            TransactionLog::remove(self.repo.path())?;
+        }
+        println!("🐑 project has been un-sheep'd");
+        Ok(())
+    }
+
+    /// Retracts a release by deleting its tag, locally and from the remote. The release branch is
+    /// deleted and the release commit reverted on the default branch when the options ask for it.
+    /// Everything is checked, and confirmed unless `yes` is set, before anything is changed.
+    pub fn undo(&self, version: &Version, options: &UndoOptions) -> Result<(), SheepError> {
+        let repo = &self.repo;
+        let repo_config = &self.config.repository;
+        let version_update = VersionUpdate {
+            current_version: version.clone(),
+            next_version: version.clone(),
+        };
+        let project_strings = ProjectStrings::new(&self.config, &version_update);
+        let tag_name = &project_strings.tag_name;
+        let remote_name = &project_strings.remote_name;
+        let tag_commit = GitTags::new().tag_commit(repo, tag_name).map_err(|error| {
+            SheepError::validation(&format!("no tag {tag_name} found")).with_source(error)
+        })?;
+        let revert_commit = if options.revert_commit {
+            Some(self.release_commit(tag_commit, &project_strings)?)
+        } else {
+            None
+        };
+
+        let remotes = self.remotes();
+        let push = repo_config.enable_push;
+        if push && !remotes.has_remote(repo, remote_name) {
+            let message = format!(
+                "no remote {remote_name} to undo the release on, set enable_push = false to undo \
+                it locally"
+            );
+            return Err(SheepError::validation(&message));
+        }
+        // git refuses to delete the checked out branch, which would otherwise only be found after
+        // the remote refs were deleted
+        let branch_name = &project_strings.branch_name;
+        let head = repo.head()?;
+        if options.delete_branch && head.is_branch() && head.shorthand() == Some(branch_name) {
+            let message = format!(
+                "{branch_name} is checked out, check out another branch to delete it"
+            );
+            return Err(SheepError::validation(&message));
+        }
+        let location = if push {
+            format!("locally and from {remote_name}")
+        } else {
+            "locally".to_string()
+        };
+
+        println!("🐑 undoing release {version}");
+        println!("   deleting tag {tag_name} {location}");
+        if options.delete_branch {
+            println!("   deleting branch {branch_name} {location}");
+        }
+        if let Some(commit) = revert_commit {
+            println!("   reverting commit {commit} on {}", repo_config.default_branch);
+        }
+        if !options.yes && !prompt::confirm("undo the release?")? {
+            println!("🐑 nothing was undone");
+            return Ok(());
+        }
+
+        // Remote refs go first so a failure leaves the local refs to retry with
+        if push {
+            println!("🗑  deleting tag {tag_name} from {remote_name}");
+            remotes.delete_tag(repo, tag_name, remote_name)?;
+            if options.delete_branch {
+                println!("🗑  deleting branch {branch_name} from {remote_name}");
+                remotes.delete_branch(repo, branch_name, remote_name)?;
+            }
+        }
+        if options.delete_branch {
+            println!("🗑  deleting branch {branch_name}");
+            let branches = GitBranches::new();
+            if branches.has_branch(repo, branch_name) {
+                branches.delete_branch(repo, branch_name)?;
+            }
+        }
+        println!("🗑  deleting tag {tag_name}");
+        GitTags::new().delete_tag(repo, tag_name)?;
+        if let Some(commit) = revert_commit {
+            println!("⏪ reverting commit {commit}");
+            GitCommits::with_default_branch(&repo_config.default_branch)
+                .with_signing(repo_config.sign_commits)
+                .revert(repo, commit)?;
+            if push {
+                println!("🚀 pushing to remote {remote_name}");
+                remotes.push_branch(repo, &repo_config.default_branch, remote_name)?;
+            }
+        }
+        println!("🐑 release {version} has been un-sheep'd");
+        Ok(())
+    }
+
+    /// Checks the tagged commit is a release commit which can be reverted from HEAD, which must
+    /// be the default branch. Releases made with `enable_branch` are tagged on their release
+    /// branch, so their commit isn't on the default branch and can't be reverted; deleting the
+    /// branch undoes them instead.
+    fn release_commit(
+        &self,
+        tag_commit: Oid,
+        project_strings: &ProjectStrings,
+    ) -> Result<Oid, SheepError> {
+        let repo = &self.repo;
+        let default_branch = &self.config.repository.default_branch;
+        let head = repo.head()?;
+        if head.shorthand() != Some(default_branch.as_str()) {
+            let message = format!("check out {default_branch} to revert the release commit");
+            return Err(SheepError::validation(&message));
+        }
+        let commit = repo.find_commit(tag_commit)?;
+        if commit.summary() != Some(project_strings.commit_message.as_str()) {
+            let message = format!(
+                "commit {tag_commit} isn't a release commit, expected message {}",
+                project_strings.commit_message
+            );
+            return Err(SheepError::validation(&message));
+        }
+        let head_commit = head.peel_to_commit()?.id();
+        if head_commit != tag_commit && !repo.graph_descendant_of(head_commit, tag_commit)? {
+            let message = format!(
+                "release commit {tag_commit} isn't on {default_branch}, releases made on a \
+                release branch are undone with --branch rather than --revert"
+            );
+            return Err(SheepError::validation(&message));
+        }
+        Ok(tag_commit)
+    }
+
+    /// Checks the config against the repository, returning a description of each problem found.
+    /// Every transform's file must exist and contain its find string for the current version,
+    /// and tag and branch patterns must contain `{version}`.
+    pub fn check(&self) -> Result<Vec<String>, SheepError> {
+        let mut problems = self.config.pattern_problems();
+        let current_version = self.current_version()?;
+        problems.extend(self.transformer.check(&self.config.transforms, &current_version));
+        for package in &self.config.packages {
+            let package_project = self.package_project(&package.name)?;
+            let package_version = package_project.current_version()?;
+            let transforms = &package_project.config.transforms;
+            problems.extend(self.transformer.check(transforms, &package_version));
+            for dependency in &package.dependencies {
+                let dependency_version = self.package_project(&dependency.name)?.current_version()?;
+                let transforms: Vec<TransformConfig> = dependency
+                    .transforms
+                    .iter()
+                    .map(|transform| TransformConfig {
+                        path: package.relative_path(&transform.path),
+                        ..transform.clone()
+                    })
+                    .collect();
+                problems.extend(self.transformer.check(&transforms, &dependency_version));
+            }
+        }
+        Ok(problems)
+    }
+
+    fn current_version(&self) -> Result<Version, SheepError> {
+        let version_constraint = self.version_constraint()?;
+        let project_version = ProjectVersion::new(self, version_constraint.as_ref())?;
+        Ok(project_version.current_version())
+    }
 
+    /// Forgets the changes recorded by the previous run, so a rollback only undoes this one.
+    pub fn clear_transaction_log(&self) -> Result<(), SheepError> {
+        TransactionLog::remove(self.repo.path())
+    }
+
+    /// The run state of this project, or of its package when releasing a package.
+    fn run_state_path(&self) -> PathBuf {
+        let package = self.package.as_ref().map(|package| package.name.as_str());
+        RunState::path(self.repo.path(), package)
+    }
+
+    pub fn print_tags(&self) -> Result<(), SheepError> {
+        let version_constraint = self.version_constraint()?;
+        let project_version = ProjectVersion::new(self, version_constraint.as_ref())?;
+        for (tag_name, tag_match) in project_version.tag_matches() {
+            match tag_match {
+                TagMatch::Accepted { version, pattern } => {
+                    println!("✅ {tag_name} -> {version} (pattern {pattern})")
+                }
+                TagMatch::Rejected { reason } => println!("❌ {tag_name}: {reason}"),
+            }
+        }
         Ok(())
     }
 
+    /// Finds the changes made to each configured package since its latest tag.
+    pub fn package_changes(&self) -> Result<Vec<PackageChanges>, SheepError> {
+        self.config
+            .packages
+            .iter()
+            .map(|package| self.package_project(&package.name)?.changes())
+            .collect()
+    }
+
+    /// Returns the names of the packages to release. This is either the provided package or all
+    /// packages, optionally limited to packages which changed since their latest tag.
+    pub fn release_package_names(
+        &self,
+        package_name: Option<&str>,
+        changed_only: bool,
+    ) -> Result<Vec<String>, SheepError> {
+        if self.config.packages.is_empty() {
+            return Err(SheepError::config("no packages found in config"));
+        }
+        let names = match package_name {
+            Some(name) => vec![self.config.package(name)?.name.clone()],
+            None => self
+                .config
+                .packages
+                .iter()
+                .map(|package| package.name.clone())
+                .collect(),
+        };
+        if !changed_only {
+            return Ok(names);
+        }
+
+        let changed_names = self
+            .package_changes()?
+            .into_iter()
+            .filter(|changes| changes.has_changes() && names.contains(&changes.name))
+            .map(|changes| changes.name)
+            .collect();
+        Ok(changed_names)
+    }
+
+    /// Opens a second handle to this project's repository, narrowed to the provided package.
+    fn package_project(&self, package_name: &str) -> Result<Project, SheepError> {
+        let repo = GitOpener::new().open(self.repo.path())?;
+        let project = Project {
+            config: self.config.clone(),
+            repo,
+            transformer: ProjectTransformer::new(self.repo_path()),
+            is_dry_run_project: self.is_dry_run_project,
+            package: None,
+            dependency_updates: vec![],
+            subproject_options: SubprojectOptions::default(),
+        };
+        project.with_package(package_name)
+    }
+
+    fn changes(&self) -> Result<PackageChanges, SheepError> {
+        let package = self
+            .package
+            .as_ref()
+            .ok_or_else(|| SheepError::config("changes are only tracked for packages"))?;
+        let version_constraint = self.version_constraint()?;
+        let project_version = ProjectVersion::new(self, version_constraint.as_ref())?;
+        let latest_tag = project_version.latest_tag();
+
+        let changed_paths = match &latest_tag {
+            None => vec![],
+            Some(tag_name) => {
+                let tag_commit = GitTags::new().tag_commit(&self.repo, tag_name)?;
+                GitDiffs::new()
+                    .changed_paths(&self.repo, tag_commit, &package.path)?
+                    .iter()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect()
+            }
+        };
+        Ok(PackageChanges {
+            name: package.name.clone(),
+            latest_tag,
+            changed_paths,
+        })
+    }
+
+    /// Remotes are authenticated with the configured ssh key.
+    fn remotes(&self) -> GitRemotes {
+        GitRemotes::new().with_ssh_key_path(self.config.repository.ssh_key_path.as_deref())
+    }
+
+    fn repo_path(&self) -> PathBuf {
+        let mut repo_path = self.repo.path().to_path_buf();
+        repo_path.pop(); // remove the .git path component
+        repo_path
+    }
+
+    fn version_constraint(&self) -> Result<Option<VersionConstraint>, SheepError> {
+        let constraints = &self.config.repository.version_constraints;
+        if constraints.is_empty() {
+            return Ok(None);
+        }
+
+        let head = self.repo.head()?;
+        let branch = head.shorthand().unwrap_or_default();
+        let constraint = VersionConstraint::for_branch(constraints, branch)?;
+        if let Some(constraint) = &constraint {
+            println!(
+                "🔒 branch {} is limited to versions {}",
+                constraint.branch, constraint.requirement
+            );
+        }
+        Ok(constraint)
+    }
+
+    /// Runs the on_failure script, only printing its own failure so the release's error is kept.
+    fn run_on_failure(&self, script_runner: &ScriptRunner) {
+        let on_failure = self.config.scripts.on_failure.as_ref();
+        if let Err(script_error) = script_runner.run(on_failure, "on_failure") {
+            println!("⚠️  on_failure script failed: {}", script_error.report());
+        }
+    }
+
+    /// Runs scripts from the package's directory when releasing a package, otherwise the repo's.
+    fn script_runner(
+        &self,
+        operation: &Operation,
+        version_update: &VersionUpdate,
+        project_strings: &ProjectStrings,
+    ) -> ScriptRunner {
+        let repo_root = self.repo_path();
+        let mut working_dir = repo_root.clone();
+        if let Some(package) = &self.package {
+            working_dir.push(&package.path);
+        }
+        let subproject_options = &self.subproject_options;
+        let script_runner = ScriptRunner::new(working_dir, version_update.clone())
+            .with_repo_root(repo_root.clone())
+            .with_shell(self.config.scripts.shell.clone())
+            .with_env("SHEEPIT_BRANCH_NAME", &project_strings.branch_name)
+            .with_env("SHEEPIT_BUMP", operation.bump_name())
+            .with_env("SHEEPIT_COMMIT_MESSAGE", &project_strings.commit_message)
+            .with_env("SHEEPIT_DRY_RUN", self.is_dry_run_project)
+            .with_env("SHEEPIT_REMOTE_NAME", &project_strings.remote_name)
+            .with_env("SHEEPIT_REPO_ROOT", repo_root.to_string_lossy())
+            .with_env("SHEEPIT_SUBPROJECT_DEPTH", subproject_options.depth())
+            .with_env("SHEEPIT_TAG_NAME", &project_strings.tag_name);
+        match subproject_options.parent_url() {
+            Some(parent_url) => script_runner.with_env("SHEEPIT_PARENT_URL", parent_url),
+            None => script_runner,
+        }
+    }
+
     fn update_repo(
         &self,
         repo_config: &RepoConfig,
         project_strings: &ProjectStrings,
-        version_update: &VersionUpdate,
+        script_runner: &ScriptRunner,
+        transaction: &mut TransactionLog,
     ) -> Result<(), SheepError> {
         let repo = &self.repo;
-        let mut working_dir = self.repo.path().to_path_buf();
-        working_dir.pop(); // remove the .git path component
-
         let scripts = &self.config.scripts;
-        let script_runner = ScriptRunner::new(working_dir, version_update.clone());
+        let version_update = script_runner.version_update();
+
+        let transforms = &self.config.transforms;
+        let has_dependency_transforms = self
+            .dependency_updates
+            .iter()
+            .any(|dependency_update| !dependency_update.transforms.is_empty());
+        let will_commit = repo_config.enable_commit
+            && (!transforms.is_empty()
+                || has_dependency_transforms
+                || scripts.after_transforms.is_some()
+                || scripts.before_commit.is_some());
+        let mut staging =
+            CommitStaging::new(&repo_config.commit_include, &repo_config.commit_exclude)?;
+        if let Some(package) = &self.package {
+            staging = staging.with_scope(&package.path);
+        }
+        let statuses = GitStatuses::new();
+        if will_commit {
+            // Check before anything is changed so unrelated work in progress stops the release
+            // early, before a release branch is left behind
+            staging.commit_paths(&statuses.dirty_paths(repo)?, &BTreeSet::new())?;
+        }
 
         // Create branch if enabled in configuration
         if repo_config.enable_branch {
+            script_runner.run(scripts.before_branch.as_ref(), "before_branch")?;
             println!("🌲 creating branch {}", &project_strings.branch_name);
             let branches = GitBranches::new();
             branches.create_branch(repo, &project_strings.branch_name)?;
+            transaction.record(Mutation::BranchCreated {
+                name: project_strings.branch_name.clone(),
+            })?;
+            transaction.record(Mutation::head_moved(repo)?)?;
             branches.checkout_branch(repo, &project_strings.branch_name)?;
         }
         // Create commit if enabled in configuration and we have transforms
-        let transforms = &self.config.transforms;
-        if repo_config.enable_commit && (!transforms.is_empty() || scripts.before_commit.is_some())
-        {
+        if will_commit {
             println!("🤖 applying transforms");
-            self.transformer.transform(transforms, version_update)?;
+            let mut transformed_paths: BTreeSet<PathBuf> = self
+                .transformer
+                .transform(transforms, version_update)?
+                .into_iter()
+                .map(PathBuf::from)
+                .collect();
+            for dependency_update in &self.dependency_updates {
+                println!(
+                    "🔗 updating dependency {} to {}",
+                    dependency_update.name, dependency_update.version_update.next_version
+                );
+                let paths = self
+                    .transformer
+                    .transform(&dependency_update.transforms, &dependency_update.version_update)?;
+                transformed_paths.extend(paths.into_iter().map(PathBuf::from));
+            }
+            script_runner.run(scripts.after_transforms.as_ref(), "after_transforms")?;
 
-            script_runner.run(scripts.before_commit.clone(), "before_commit")?;
+            script_runner.run(scripts.before_commit.as_ref(), "before_commit")?;
 
+            let changed_paths = statuses.dirty_paths(repo)?;
+            let commit_paths = staging.commit_paths(&changed_paths, &transformed_paths)?;
+            let out_of_scope_paths = staging.out_of_scope_paths(&changed_paths, &transformed_paths);
+            if !out_of_scope_paths.is_empty() {
+                println!("⚠️  leaving out changes outside the package:");
+                for path in &out_of_scope_paths {
+                    println!("   {}", path.to_string_lossy());
+                }
+            }
             println!("✍️  committing changes");
-            let commits = GitCommits::with_default_branch(&repo_config.default_branch);
-            commits.commit(repo, &project_strings.commit_message)?;
+            for path in &commit_paths {
+                println!("   {}", path.to_string_lossy());
+            }
+            let commit = GitCommits::with_default_branch(&repo_config.default_branch)
+                .with_signing(repo_config.sign_commits)
+                .with_paths(&commit_paths)
+                .commit(repo, &project_strings.commit_message)?;
+            transaction.record(Mutation::CommitCreated {
+                commit: commit.to_string(),
+                paths: commit_paths,
+            })?;
+            script_runner.run(scripts.after_commit.as_ref(), "after_commit")?;
         }
         // Create tag if enabled in configuration
         if repo_config.enable_tag {
+            script_runner.run(scripts.before_tag.as_ref(), "before_tag")?;
             println!("️🏷  creating tag {}", &project_strings.tag_name);
             let tags = GitTags::new();
             tags.create_tag(repo, &project_strings.tag_name, None)?;
+            transaction.record(Mutation::TagCreated {
+                name: project_strings.tag_name.clone(),
+            })?;
+            script_runner.run(scripts.after_tag.as_ref(), "after_tag")?;
         }
         // Push if enabled in configuration
         if repo_config.enable_push && !self.is_dry_run_project {
+            script_runner.run(scripts.before_push.as_ref(), "before_push")?;
             println!("🚀 pushing to remote {}", &project_strings.remote_name);
 
-            let remotes = GitRemotes::new();
+            let remotes = self.remotes();
             if repo_config.enable_branch {
                 remotes.push_branch(
                     repo,
                     &project_strings.branch_name,
                     &project_strings.remote_name,
                 )?;
+                transaction.record(Mutation::BranchPushed {
+                    name: project_strings.branch_name.clone(),
+                    remote: project_strings.remote_name.clone(),
+                })?;
             } else if repo_config.enable_commit {
+                // The default branch existed before the release, so it's never deleted on rollback
                 remotes.push_branch(
                     repo,
                     &repo_config.default_branch,
@@ -166,32 +892,264 @@ impl Project {
                     &project_strings.tag_name,
                     &project_strings.remote_name,
                 )?;
+                transaction.record(Mutation::TagPushed {
+                    name: project_strings.tag_name.clone(),
+                    remote: project_strings.remote_name.clone(),
+                })?;
             }
+            script_runner.run(scripts.after_push.as_ref(), "after_push")?;
         }
         Ok(())
     }
+}
 
-    fn update_subprojects(&self, version_update: &VersionUpdate) -> Result<(), SheepError> {
-        let configs = &self.config.subprojects;
-        let is_dry_run = self.is_dry_run_project;
-        if configs.is_empty() {
-            return Ok(());
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::file::{MockFileReader, MockFileWriter};
+    use crate::project::operation::{BumpMode, Operation};
+    use crate::project::options::UndoOptions;
+    use crate::project::run_state::PlanState;
+    use crate::project::subproject::SubprojectOptions;
+    use crate::project::Project;
+    use crate::repo::branch::GitBranches;
+    use crate::repo::remote::GitRemotes;
+    use crate::repo::tag::GitTags;
+    use crate::repo::testing;
+    use crate::transform::project_transform::ProjectTransformer;
+    use git2::Repository;
+    use semver::Version;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn undo() {
+        let (temp_dir, repo) = testing::init_repo();
+        let remote_dir = TempDir::new().unwrap();
+        let remote = Repository::init_bare(remote_dir.path()).unwrap();
+        repo.remote("origin", &remote_dir.path().to_string_lossy()).unwrap();
+        testing::commit(&repo, "first");
+        GitTags::new().create_tag(&repo, "1.0.0", None).unwrap();
+        GitRemotes::new().push_tag(&repo, "1.0.0", "origin").unwrap();
+        let project = project(&temp_dir, repo, "");
+
+        project.undo(&version(), &undo_options()).expect("failed to undo");
+        assert!(project.repo.find_reference("refs/tags/1.0.0").is_err());
+        assert!(remote.find_reference("refs/tags/1.0.0").is_err())
+    }
+
+    #[test]
+    fn undo_tag_missing_from_remote() {
+        let (temp_dir, repo) = testing::init_repo();
+        let remote_dir = TempDir::new().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        repo.remote("origin", &remote_dir.path().to_string_lossy()).unwrap();
+        testing::commit(&repo, "first");
+        GitTags::new().create_tag(&repo, "1.0.0", None).unwrap();
+        let project = project(&temp_dir, repo, "");
+
+        project.undo(&version(), &undo_options()).expect("failed to undo");
+        assert!(project.repo.find_reference("refs/tags/1.0.0").is_err())
+    }
+
+    #[test]
+    fn undo_without_push() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        GitTags::new().create_tag(&repo, "1.0.0", None).unwrap();
+        let project = project(&temp_dir, repo, "[repository]\nenable_push = false");
+
+        project.undo(&version(), &undo_options()).expect("failed to undo");
+        assert!(project.repo.find_reference("refs/tags/1.0.0").is_err())
+    }
+
+    #[test]
+    fn undo_missing_remote() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        GitTags::new().create_tag(&repo, "1.0.0", None).unwrap();
+        let project = project(&temp_dir, repo, "");
+
+        project.undo(&version(), &undo_options()).expect_err("should fail");
+        assert!(project.repo.find_reference("refs/tags/1.0.0").is_ok())
+    }
+
+    #[test]
+    fn undo_missing_tag() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        let project = project(&temp_dir, repo, "[repository]\nenable_push = false");
+
+        project.undo(&version(), &undo_options()).expect_err("should fail");
+    }
+
+    #[test]
+    fn undo_on_release_branch() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        GitTags::new().create_tag(&repo, "1.0.0", None).unwrap();
+        let branches = GitBranches::new();
+        branches.create_branch(&repo, "release/1.0.0").unwrap();
+        branches.checkout_branch(&repo, "release/1.0.0").unwrap();
+        let config = "[repository]\nenable_push = false\nbranch_pattern = 'release/{version}'";
+        let project = project(&temp_dir, repo, config);
+        let options = UndoOptions { delete_branch: true, ..undo_options() };
+
+        let error = project.undo(&version(), &options).expect_err("should fail");
+        assert!(error.message().contains("release/1.0.0 is checked out"));
+        assert!(project.repo.find_reference("refs/tags/1.0.0").is_ok())
+    }
+
+    #[test]
+    fn update_runs_hooks_in_order() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        let project = project(&temp_dir, repo, &hooks_config(""));
+
+        project.update(patch()).expect("failed to update");
+        let expected = "before_all\nafter_version_computed\nbefore_tag\nafter_tag\n";
+        assert_eq!(expected, hooks_log(&temp_dir))
+    }
+
+    #[test]
+    fn update_failing_before_hook_stops_stage() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        let project = project(&temp_dir, repo, &hooks_config("before_tag = 'exit 1'"));
+
+        project.update(patch()).expect_err("should fail");
+        let expected = "before_all\nafter_version_computed\non_failure\n";
+        assert_eq!(expected, hooks_log(&temp_dir));
+        assert!(GitTags::new().get_tags(&project.repo).unwrap().is_empty())
+    }
+
+    #[test]
+    fn update_refused_version_runs_on_failure() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        let constraint = "[repository.version_constraints]\n'*' = '2.*'";
+        let config = format!("{constraint}\n{}", hooks_config(""));
+        let project = project(&temp_dir, repo, &config);
+
+        project.update(patch()).expect_err("should fail");
+        assert_eq!("on_failure\n", hooks_log(&temp_dir))
+    }
+
+    #[test]
+    fn update_failing_subproject_runs_on_failure() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        let config = format!("{}\n[[subprojects]]\npath = 'missing'", hooks_config(""));
+        let project = project(&temp_dir, repo, &config);
+
+        project.update(patch()).expect_err("should fail");
+        let expected = "before_all\nafter_version_computed\nbefore_tag\nafter_tag\non_failure\n";
+        assert_eq!(expected, hooks_log(&temp_dir))
+    }
+
+    #[test]
+    fn update_failing_after_push_keeps_pushed_release() {
+        let (temp_dir, repo) = testing::init_repo();
+        let remote_dir = TempDir::new().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        repo.remote("origin", &remote_dir.path().to_string_lossy()).unwrap();
+        testing::commit(&repo, "first");
+        let config = "[repository]\nenable_commit = false\n[scripts]\nafter_push = 'false'";
+        let project = project(&temp_dir, repo, config);
+
+        project.update(patch()).expect_err("should fail");
+        assert!(project.repo.find_reference("refs/tags/1.0.1").is_ok())
+    }
+
+    #[test]
+    fn release_resume_skips_released_packages() {
+        let (temp_dir, repo) = testing::init_repo();
+        let config = r#"
+            [repository]
+            enable_commit = false
+            enable_push = false
+
+            [[packages]]
+            name = 'a'
+            path = 'a'
+            tag_pattern = 'a-{version}'
+
+            [[packages]]
+            name = 'b'
+            path = 'b'
+            tag_pattern = 'b-{version}'
+
+            [packages.scripts]
+            before_tag = 'test -f "$SHEEPIT_REPO_ROOT/ok"'
+        "#;
+        testing::write_file(&repo, "a/a.txt", "a");
+        testing::write_file(&repo, "b/b.txt", "b");
+        testing::commit(&repo, "packages");
+        let root_project = project(&temp_dir, repo, config);
+        let plan = root_project.release_plan(&["a".to_string(), "b".to_string()]).unwrap();
+        let open_package = |package: &str| {
+            let repo = Repository::open(temp_dir.path()).unwrap();
+            project(&temp_dir, repo, config).with_package(package)
+        };
+
+        root_project.release(&plan, patch(), false, false, open_package).expect_err("b should fail");
+        assert_eq!(vec!["a-1.0.1"], tag_names(&root_project.repo));
+
+        fs::write(temp_dir.path().join("ok"), "").unwrap();
+        root_project.release(&plan, patch(), false, true, open_package).expect("resume failed");
+        assert_eq!(vec!["a-1.0.1", "b-1.0.1"], tag_names(&root_project.repo));
+        assert!(!PlanState::path(root_project.repo.path()).exists())
+    }
+
+    fn tag_names(repo: &Repository) -> Vec<String> {
+        let tags = repo.tag_names(None).unwrap();
+        tags.iter().flatten().map(str::to_string).collect()
+    }
+
+    /// Records each hook in `hooks.log`, with `scripts` overriding the recording scripts.
+    fn hooks_config(scripts: &str) -> String {
+        let hooks = ["before_all", "after_version_computed", "before_tag", "after_tag", "on_failure"];
+        let mut lines = vec![
+            "[repository]".to_string(),
+            "enable_push = false".to_string(),
+            "[scripts]".to_string(),
+            scripts.to_string(),
+        ];
+        for hook in hooks.iter().filter(|hook| !scripts.starts_with(*hook)) {
+            lines.push(format!("{hook} = 'echo {hook} >> hooks.log'"));
         }
+        lines.join("\n")
+    }
 
-        let directory = temp::directory()?;
+    fn hooks_log(temp_dir: &TempDir) -> String {
+        fs::read_to_string(temp_dir.path().join("hooks.log")).unwrap_or_default()
+    }
 
-        for config in configs {
-            let operation = Operation::SetVersion {
-                current_version: Some(version_update.current_version.clone()),
-                next_version: version_update.next_version.clone(),
-            };
-            let url = &config.repo_url;
-            let project = Self::new_remote_project(url, &directory, is_dry_run)?;
-            println!("------------");
-            println!("🚢 sheep'n subproject {}", url);
-            project.update(operation)?;
+    fn patch() -> Operation {
+        Operation::BumpVersion(BumpMode::Patch)
+    }
+
+    fn project(temp_dir: &TempDir, repo: Repository, config: &str) -> Project {
+        Project {
+            config: toml::from_str::<Config>(config).unwrap(),
+            repo,
+            transformer: ProjectTransformer::for_tests(
+                MockFileReader::default(),
+                MockFileWriter::default(),
+                temp_dir.path().to_path_buf(),
+            ),
+            is_dry_run_project: false,
+            package: None,
+            dependency_updates: vec![],
+            subproject_options: SubprojectOptions::default(),
         }
+    }
 
-        Ok(())
+    fn undo_options() -> UndoOptions {
+        UndoOptions { yes: true, ..UndoOptions::default() }
+    }
+
+    fn version() -> Version {
+        Version::new(1, 0, 0)
     }
 }
@@ -0,0 +1,76 @@
+use std::fmt::{Display, Formatter};
+
+/// The changes made to a package since it was last released.
+#[derive(Debug, PartialEq)]
+pub struct PackageChanges {
+    pub name: String,
+    pub latest_tag: Option<String>,
+    pub changed_paths: Vec<String>,
+}
+
+impl PackageChanges {
+    /// Packages which have never been tagged are always considered changed.
+    pub fn has_changes(&self) -> bool {
+        self.latest_tag.is_none() || !self.changed_paths.is_empty()
+    }
+}
+
+impl Display for PackageChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.latest_tag {
+            None => write!(f, "📦 {}: no previous release", self.name),
+            Some(tag) if self.changed_paths.is_empty() => {
+                write!(f, "💤 {}: unchanged since {tag}", self.name)
+            }
+            Some(tag) => {
+                let count = self.changed_paths.len();
+                write!(f, "📦 {}: {count} file(s) changed since {tag}", self.name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::project::changes::PackageChanges;
+
+    #[test]
+    fn has_changes_no_tag() {
+        let changes = PackageChanges {
+            name: "api".to_string(),
+            latest_tag: None,
+            changed_paths: vec![],
+        };
+        assert!(changes.has_changes())
+    }
+
+    #[test]
+    fn has_changes_changed_paths() {
+        let changes = PackageChanges {
+            name: "api".to_string(),
+            latest_tag: Some("api-1.0.0".to_string()),
+            changed_paths: vec!["api/lib.rs".to_string()],
+        };
+        assert!(changes.has_changes())
+    }
+
+    #[test]
+    fn has_changes_unchanged() {
+        let changes = PackageChanges {
+            name: "api".to_string(),
+            latest_tag: Some("api-1.0.0".to_string()),
+            changed_paths: vec![],
+        };
+        assert!(!changes.has_changes())
+    }
+
+    #[test]
+    fn display() {
+        let changes = PackageChanges {
+            name: "api".to_string(),
+            latest_tag: Some("api-1.0.0".to_string()),
+            changed_paths: vec!["api/lib.rs".to_string()],
+        };
+        assert_eq!("📦 api: 1 file(s) changed since api-1.0.0", changes.to_string())
+    }
+}
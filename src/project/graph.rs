@@ -0,0 +1,381 @@
+use crate::config::{PackageConfig, TransformConfig};
+use crate::{token, SheepError};
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Dependencies between the packages in a repository, keyed by package name.
+#[derive(Debug, Default, PartialEq)]
+pub struct PackageGraph {
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl PackageGraph {
+    /// Builds the graph from the dependencies declared in config.
+    pub fn from_config(packages: &[PackageConfig]) -> Result<PackageGraph, SheepError> {
+        let mut graph = PackageGraph::default();
+        for package in packages {
+            graph.dependencies.entry(package.name.clone()).or_default();
+            for dependency in &package.dependencies {
+                if !packages.iter().any(|other| other.name == dependency.name) {
+                    let message = format!(
+                        "package {} depends on unknown package {}",
+                        package.name, dependency.name
+                    );
+                    return Err(SheepError::config(&message));
+                }
+                graph.add_dependency(&package.name, &dependency.name);
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Adds dependencies found in each package's `Cargo.toml` or `package.json`. Packages are
+    /// matched by the names they're published under, or by their name in config when they have
+    /// no manifest.
+    pub fn infer_from_manifests<P: AsRef<Path>>(&mut self,
+                                                repo_path: P,
+                                                packages: &[PackageConfig]) {
+        let manifests: Vec<(&PackageConfig, Manifest)> = packages
+            .iter()
+            .map(|package| {
+                let package_path = repo_path.as_ref().join(&package.path);
+                (package, Manifest::read(&package_path))
+            })
+            .collect();
+
+        for (package, manifest) in &manifests {
+            for (other, other_manifest) in &manifests {
+                let is_dependency = package.name != other.name
+                    && other_manifest.published_names(other)
+                        .iter()
+                        .any(|name| manifest.dependencies.contains(name));
+                if is_dependency {
+                    self.add_dependency(&package.name, &other.name);
+                }
+            }
+        }
+    }
+
+    pub fn dependencies(&self, package: &str) -> BTreeSet<String> {
+        self.dependencies.get(package).cloned().unwrap_or_default()
+    }
+
+    fn add_dependency(&mut self, package: &str, dependency: &str) {
+        self.dependencies
+            .entry(package.to_string())
+            .or_default()
+            .insert(dependency.to_string());
+        self.dependencies.entry(dependency.to_string()).or_default();
+    }
+
+    /// Returns the provided packages plus every package which transitively depends on them.
+    pub fn with_dependents(&self, packages: &[String]) -> BTreeSet<String> {
+        let mut result: BTreeSet<String> = packages.iter().cloned().collect();
+        let mut pending: Vec<String> = packages.to_vec();
+        while let Some(package) = pending.pop() {
+            for (dependent, dependencies) in &self.dependencies {
+                if dependencies.contains(&package) && result.insert(dependent.clone()) {
+                    pending.push(dependent.clone());
+                }
+            }
+        }
+        result
+    }
+
+    /// Orders the packages so each package comes after the packages it depends on.
+    pub fn topological_order(&self, packages: &BTreeSet<String>) -> Result<Vec<String>, SheepError> {
+        let mut remaining = packages.clone();
+        let mut ordered = Vec::new();
+        while !remaining.is_empty() {
+            let ready: Vec<String> = remaining
+                .iter()
+                .filter(|package| {
+                    self.dependencies(package)
+                        .iter()
+                        .all(|dependency| !remaining.contains(dependency))
+                })
+                .cloned()
+                .collect();
+            if ready.is_empty() {
+                let cycle = remaining.into_iter().collect::<Vec<_>>().join(", ");
+                let message = format!("dependency cycle between packages: {cycle}");
+                return Err(SheepError::config(&message));
+            }
+            for package in ready {
+                remaining.remove(&package);
+                ordered.push(package);
+            }
+        }
+        Ok(ordered)
+    }
+}
+
+/// Transforms updating the package's requirement on a dependency inferred from its manifests,
+/// which has no configured transforms. Each manifest line naming the dependency along with its
+/// current version, such as `my-core = "^1.2.0"`, is updated to the next version. Fails when no
+/// such line is found, such as for `my-core = "1"` or `my-core.workspace = true`, rather than
+/// releasing the package with a stale requirement.
+pub fn requirement_transforms<P: AsRef<Path>>(
+    repo_path: P,
+    package: &PackageConfig,
+    dependency: &PackageConfig,
+    current_version: &Version,
+) -> Result<Vec<TransformConfig>, SheepError> {
+    let repo_path = repo_path.as_ref();
+    let names = Manifest::read(&repo_path.join(&dependency.path)).published_names(dependency);
+    let version = current_version.to_string();
+    let mut transforms = vec![];
+    for file_name in MANIFEST_FILES {
+        let Ok(text) = fs::read_to_string(repo_path.join(&package.path).join(file_name)) else {
+            continue;
+        };
+        for line in text.lines().map(str::trim) {
+            let names_dependency = names.iter().any(|name| {
+                let key = line.strip_prefix(name.as_str())
+                    .or_else(|| line.strip_prefix(&format!("\"{name}\"")));
+                key.is_some_and(|rest| rest.trim_start().starts_with(['=', ':']))
+            });
+            if names_dependency && line.contains(&version) {
+                transforms.push(TransformConfig {
+                    path: package.relative_path(file_name),
+                    find: None,
+                    replace: line.replacen(&version, token::VERSION, 1),
+                });
+            }
+        }
+    }
+    if transforms.is_empty() {
+        let message = format!(
+            "package {} depends on {} but no requirement on {version} was found in its manifests, \
+            add transforms for the dependency under [[packages.dependencies]]",
+            package.name, dependency.name
+        );
+        return Err(SheepError::config(&message));
+    }
+    Ok(transforms)
+}
+
+const MANIFEST_FILES: [&str; 2] = ["Cargo.toml", "package.json"];
+
+/// The names a package is published under and the names of its dependencies.
+#[derive(Debug, Default)]
+struct Manifest {
+    names: BTreeSet<String>,
+    dependencies: BTreeSet<String>,
+}
+
+impl Manifest {
+    fn read(package_path: &Path) -> Manifest {
+        let mut manifest = Manifest::default();
+        if let Ok(text) = fs::read_to_string(package_path.join("Cargo.toml")) {
+            manifest.add_cargo(&text);
+        }
+        if let Ok(text) = fs::read_to_string(package_path.join("package.json")) {
+            manifest.add_npm(&text);
+        }
+        manifest
+    }
+
+    /// The names the package is published under, or its name in config without a manifest.
+    fn published_names(&self, package: &PackageConfig) -> BTreeSet<String> {
+        if self.names.is_empty() {
+            BTreeSet::from([package.name.clone()])
+        } else {
+            self.names.clone()
+        }
+    }
+
+    fn add_cargo(&mut self, text: &str) {
+        let Ok(value) = toml::from_str::<toml::Value>(text) else {
+            return;
+        };
+        if let Some(name) = value.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()) {
+            self.names.insert(name.to_string());
+        }
+        for table in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            if let Some(dependencies) = value.get(table).and_then(|d| d.as_table()) {
+                self.dependencies.extend(dependencies.keys().cloned());
+            }
+        }
+    }
+
+    fn add_npm(&mut self, text: &str) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(text) else {
+            return;
+        };
+        if let Some(name) = value.get("name").and_then(|n| n.as_str()) {
+            self.names.insert(name.to_string());
+        }
+        for key in ["dependencies", "devDependencies", "peerDependencies"] {
+            if let Some(dependencies) = value.get(key).and_then(|d| d.as_object()) {
+                self.dependencies.extend(dependencies.keys().cloned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{DependencyConfig, PackageConfig, TransformConfig};
+    use crate::project::graph::{requirement_transforms, Manifest, PackageGraph};
+    use semver::Version;
+    use std::collections::BTreeSet;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn from_config_unknown_dependency() {
+        let packages = vec![package("api", &["missing"])];
+        PackageGraph::from_config(&packages).expect_err("dependency should be unknown");
+    }
+
+    #[test]
+    fn with_dependents_includes_transitive_dependents() {
+        let graph = PackageGraph::from_config(&packages()).unwrap();
+        let result = graph.with_dependents(&["core".to_string()]);
+        assert_eq!(set(&["api", "core", "web"]), result)
+    }
+
+    #[test]
+    fn with_dependents_leaf_package() {
+        let graph = PackageGraph::from_config(&packages()).unwrap();
+        let result = graph.with_dependents(&["web".to_string()]);
+        assert_eq!(set(&["web"]), result)
+    }
+
+    #[test]
+    fn topological_order_dependencies_first() {
+        let graph = PackageGraph::from_config(&packages()).unwrap();
+        let order = graph.topological_order(&set(&["web", "api", "core", "docs"])).unwrap();
+        assert_eq!(vec!["core", "docs", "api", "web"], order)
+    }
+
+    #[test]
+    fn topological_order_cycle() {
+        let packages = vec![package("a", &["b"]), package("b", &["a"])];
+        let graph = PackageGraph::from_config(&packages).unwrap();
+        graph.topological_order(&set(&["a", "b"])).expect_err("should detect cycle");
+    }
+
+    #[test]
+    fn manifest_cargo() {
+        let mut manifest = Manifest::default();
+        manifest.add_cargo(
+            r#"
+            [package]
+            name = "my-api"
+
+            [dependencies]
+            my-core = { path = "../core", version = "1.0.0" }
+            serde = "1"
+            "#,
+        );
+        assert_eq!(set(&["my-api"]), manifest.names);
+        assert_eq!(set(&["my-core", "serde"]), manifest.dependencies);
+    }
+
+    #[test]
+    fn manifest_npm() {
+        let mut manifest = Manifest::default();
+        manifest.add_npm(r#"{ "name": "@org/web", "dependencies": { "@org/core": "^1.0.0" } }"#);
+        assert_eq!(set(&["@org/web"]), manifest.names);
+        assert_eq!(set(&["@org/core"]), manifest.dependencies);
+    }
+
+    #[test]
+    fn requirement_transforms_cargo() {
+        let repo_dir = TempDir::new().unwrap();
+        fs::create_dir_all(repo_dir.path().join("core")).unwrap();
+        fs::create_dir_all(repo_dir.path().join("api")).unwrap();
+        fs::write(repo_dir.path().join("core/Cargo.toml"), "[package]\nname = \"my-core\"\n").unwrap();
+        let manifest = r#"
+        [package]
+        name = "my-api"
+        version = "1.2.0"
+
+        [dependencies]
+        my-core = { path = "../core", version = "^1.2.0" }
+        "#;
+        fs::write(repo_dir.path().join("api/Cargo.toml"), manifest).unwrap();
+
+        let transforms = requirement_transforms(
+            repo_dir.path(),
+            &package("api", &[]),
+            &package("core", &[]),
+            &Version::new(1, 2, 0),
+        )
+        .expect("failed to find requirement");
+        let expected = vec![TransformConfig {
+            path: "api/Cargo.toml".to_string(),
+            find: None,
+            replace: r#"my-core = { path = "../core", version = "^{version}" }"#.to_string(),
+        }];
+        assert_eq!(expected, transforms)
+    }
+
+    #[test]
+    fn requirement_transforms_partial_version() {
+        let repo_dir = TempDir::new().unwrap();
+        fs::create_dir_all(repo_dir.path().join("api")).unwrap();
+        let manifest = "[package]\nname = \"my-api\"\n\n[dependencies]\ncore = \"1\"\n";
+        fs::write(repo_dir.path().join("api/Cargo.toml"), manifest).unwrap();
+
+        let error = requirement_transforms(
+            repo_dir.path(),
+            &package("api", &[]),
+            &package("core", &[]),
+            &Version::new(1, 2, 0),
+        )
+        .expect_err("should fail");
+        assert!(error.message().contains("no requirement on 1.2.0"))
+    }
+
+    #[test]
+    fn infer_from_manifests_published_names() {
+        let repo_dir = TempDir::new().unwrap();
+        for (path, manifest) in [
+            ("core", "[package]\nname = \"my-core\"\n"),
+            ("api", "[package]\nname = \"my-api\"\n[dependencies]\nmy-core = \"1\"\n"),
+            ("web", "[package]\nname = \"my-web\"\n[dependencies]\ncore = \"1\"\n"),
+        ] {
+            fs::create_dir_all(repo_dir.path().join(path)).unwrap();
+            fs::write(repo_dir.path().join(path).join("Cargo.toml"), manifest).unwrap();
+        }
+        let packages = vec![package("core", &[]), package("api", &[]), package("web", &[])];
+        let mut graph = PackageGraph::from_config(&packages).unwrap();
+
+        graph.infer_from_manifests(repo_dir.path(), &packages);
+        assert_eq!(set(&["core"]), graph.dependencies("api"));
+        assert!(graph.dependencies("web").is_empty())
+    }
+
+    fn packages() -> Vec<PackageConfig> {
+        vec![
+            package("core", &[]),
+            package("api", &["core"]),
+            package("web", &["api"]),
+            package("docs", &[]),
+        ]
+    }
+
+    fn package(name: &str, dependencies: &[&str]) -> PackageConfig {
+        PackageConfig {
+            name: name.to_string(),
+            path: name.to_string(),
+            dependencies: dependencies
+                .iter()
+                .map(|dependency| DependencyConfig {
+                    name: dependency.to_string(),
+                    transforms: vec![],
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn set(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+}
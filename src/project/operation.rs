@@ -1,27 +1,55 @@
 use mockall_double::double;
 use semver::Version;
+use serde::{Deserialize, Serialize};
 use crate::version::update::VersionUpdate;
 #[double]
 use crate::project::project_version::ProjectVersion;
 use crate::version::bump;
 
+#[derive(Clone, Debug)]
 pub enum Operation {
     BumpVersion(BumpMode),
+    /// Releases the configured initial version when the project has no previous version,
+    /// otherwise bumps the version like [`Operation::BumpVersion`].
+    Initial(BumpMode),
     SetVersion { current_version: Option<Version>, next_version: Version },
 }
 
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BumpMode {
     Major,
     Minor,
     Patch,
 }
 
+impl BumpMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BumpMode::Major => "major",
+            BumpMode::Minor => "minor",
+            BumpMode::Patch => "patch",
+        }
+    }
+}
+
 impl Operation {
+    /// Names the kind of version change, as exposed to scripts.
+    pub fn bump_name(&self) -> &'static str {
+        match self {
+            Operation::BumpVersion(bump_mode) | Operation::Initial(bump_mode) => bump_mode.name(),
+            Operation::SetVersion { .. } => "set",
+        }
+    }
+
     pub fn version_update(&self, project_version: &ProjectVersion) -> VersionUpdate {
         match self {
             Operation::BumpVersion(bump_mode) => {
                 Self::bump_version(project_version, bump_mode)
             }
+            Operation::Initial(bump_mode) => {
+                Self::initial_version(project_version, bump_mode)
+            }
             Operation::SetVersion { current_version, next_version } => {
                 Self::set_version(project_version, current_version, next_version)
             }
@@ -29,7 +57,11 @@ impl Operation {
     }
 
     fn bump_version(project_version: &ProjectVersion, bump_mode: &BumpMode) -> VersionUpdate {
-        let current_version = project_version.current_version();
+        let current_version = project_version.latest_version().unwrap_or_else(|| {
+            let initial_version = project_version.initial_version();
+            println!("🔍 no previous version found, bumping from {initial_version}");
+            initial_version
+        });
         let next_version = match bump_mode {
             BumpMode::Major => bump::major_version(&current_version),
             BumpMode::Minor => bump::minor_version(&current_version),
@@ -38,6 +70,18 @@ impl Operation {
         VersionUpdate { current_version, next_version }
     }
 
+    fn initial_version(project_version: &ProjectVersion, bump_mode: &BumpMode) -> VersionUpdate {
+        if project_version.latest_version().is_some() {
+            return Self::bump_version(project_version, bump_mode);
+        }
+        let initial_version = project_version.initial_version();
+        println!("🔍 no previous version found, releasing initial version {initial_version}");
+        VersionUpdate {
+            current_version: initial_version.clone(),
+            next_version: initial_version,
+        }
+    }
+
     fn set_version(project_version: &ProjectVersion,
                    current_version: &Option<Version>,
                    next_version: &Version) -> VersionUpdate {
@@ -97,6 +141,45 @@ mod test {
         assert_eq!(expected, version_update)
     }
 
+    #[test]
+    fn version_update_bump_version_without_tags() {
+        let project_version = MockProjectVersion::mock_without_tags();
+        let operation = Operation::BumpVersion(BumpMode::Patch);
+
+        let version_update = operation.version_update(&project_version);
+        let expected = VersionUpdate {
+            current_version: Version::new(0, 0, 1),
+            next_version: Version::new(0, 0, 2),
+        };
+        assert_eq!(expected, version_update)
+    }
+
+    #[test]
+    fn version_update_initial_with_tags() {
+        let project_version = MockProjectVersion::mock();
+        let operation = Operation::Initial(BumpMode::Minor);
+
+        let version_update = operation.version_update(&project_version);
+        let expected = VersionUpdate {
+            current_version: Version::new(1, 0, 0),
+            next_version: Version::new(1, 1, 0),
+        };
+        assert_eq!(expected, version_update)
+    }
+
+    #[test]
+    fn version_update_initial_without_tags() {
+        let project_version = MockProjectVersion::mock_without_tags();
+        let operation = Operation::Initial(BumpMode::Minor);
+
+        let version_update = operation.version_update(&project_version);
+        let expected = VersionUpdate {
+            current_version: Version::new(0, 0, 1),
+            next_version: Version::new(0, 0, 1),
+        };
+        assert_eq!(expected, version_update)
+    }
+
     #[test]
     fn version_update_set_version_no_current_version() {
         let project_version = MockProjectVersion::mock();
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// Options which control how a project is updated.
+#[derive(Clone, Debug, Default)]
+pub struct UpdateOptions {
+    /// A config file used in place of the one found in the repository.
+    pub config_path: Option<PathBuf>,
+    /// When true the update is applied to a fresh clone and nothing is pushed.
+    pub dry_run: bool,
+    /// The number of subprojects released at the same time.
+    pub jobs: usize,
+    /// How many levels of nested subprojects are released, `None` for no limit.
+    pub max_depth: Option<usize>,
+    /// The name of the package to release in a multi-package repository.
+    pub package: Option<String>,
+    /// When true, a release interrupted while updating subprojects is resumed.
+    pub resume: bool,
+}
+
+/// Options which control how a config is created.
+#[derive(Clone, Debug, Default)]
+pub struct InitOptions {
+    /// When true an existing config is replaced.
+    pub force: bool,
+    /// When true the detected values are used without asking.
+    pub yes: bool,
+}
+
+/// Options which control how a release is undone.
+#[derive(Clone, Debug, Default)]
+pub struct UndoOptions {
+    /// A config file used in place of the one found in the repository.
+    pub config_path: Option<PathBuf>,
+    /// When true the release branch is also deleted, locally and from the remote.
+    pub delete_branch: bool,
+    /// The name of the package whose release is undone in a multi-package repository.
+    pub package: Option<String>,
+    /// When true the release commit is reverted on the default branch.
+    pub revert_commit: bool,
+    /// When true nothing is asked before the release is undone.
+    pub yes: bool,
+}
@@ -1,55 +1,147 @@
 use semver::Version;
 use crate::config::Config;
+use crate::error::SheepError;
 use crate::project::Project;
 use crate::repo::tag::GitTags;
-use crate::token::TokenTrimmer;
+use crate::version::constraint::VersionConstraint;
 use crate::version::list::VersionList;
+use crate::version::tag_match::{TagMatch, TagMatcher};
 
 pub struct ProjectVersion<'a> {
     project: &'a Project,
+    constraint: Option<&'a VersionConstraint>,
+    matchers: Vec<TagMatcher>,
 }
 
 #[cfg_attr(test, allow(dead_code))]
 impl<'a> ProjectVersion<'a> {
-    pub fn new(project: &'a Project) -> Self {
-        Self { project }
+    pub fn new(project: &'a Project,
+               constraint: Option<&'a VersionConstraint>) -> Result<Self, SheepError> {
+        let matchers = Self::tag_matchers(&project.config)?;
+        Ok(Self { project, constraint, matchers })
     }
 
     pub fn current_version(&self) -> Version {
+        self.latest_version().unwrap_or_else(|| self.initial_version())
+    }
+
+    /// The latest version found in the repo's tags, if any.
+    pub fn latest_version(&self) -> Option<Version> {
+        let tag_list = self.tag_list();
+        let mut version_list = VersionList::from_tag_list(&tag_list, &self.matchers);
+        if let Some(constraint) = self.constraint {
+            version_list = version_list.matching(&constraint.requirement);
+        }
+        version_list.latest_version()
+    }
+
+    pub fn initial_version(&self) -> Version {
+        self.project.config.repository.initial_version.clone()
+    }
+
+    /// The name of the tag for the latest version, if any.
+    pub fn latest_tag(&self) -> Option<String> {
+        self.tag_matches()
+            .into_iter()
+            .filter_map(|(tag_name, tag_match)| {
+                tag_match.version().cloned().map(|version| (version, tag_name))
+            })
+            .max_by(|(left, _), (right, _)| left.cmp(right))
+            .map(|(_, tag_name)| tag_name)
+    }
+
+    /// Matches every tag in the repo, explaining why tags aren't used for version detection.
+    pub fn tag_matches(&self) -> Vec<(String, TagMatch)> {
         let tags = GitTags::new();
-        let tag_list = tags.get_tags(&self.project.repo).unwrap_or_default();
-        let tag_token_trimmer = Self::tag_token_trimmer(&self.project.config);
-        let version_list = VersionList::from_tag_list(&tag_list,
-                                                      tag_token_trimmer);
-        version_list.latest_version().unwrap_or(Self::default_version())
+        let all_tags = tags.get_tags(&self.project.repo).unwrap_or_default();
+        let considered_tags = self.tag_list();
+
+        all_tags.into_iter()
+            .map(|tag_name| {
+                let tag_match = if considered_tags.contains(&tag_name) {
+                    self.match_tag(&tag_name)
+                } else {
+                    TagMatch::Rejected { reason: "not reachable from HEAD".to_string() }
+                };
+                (tag_name, tag_match)
+            })
+            .collect()
     }
 
-    fn tag_token_trimmer(config: &Config) -> Option<TokenTrimmer> {
-        let tag_pattern = &config.repository.tag_pattern;
-        TokenTrimmer::new(tag_pattern, "{version}")
+    fn match_tag(&self, tag_name: &str) -> TagMatch {
+        let tag_match = TagMatch::from_matchers(tag_name, &self.matchers);
+        match (tag_match.version(), self.constraint) {
+            (Some(version), Some(constraint)) if !constraint.requirement.matches(version) => {
+                TagMatch::Rejected {
+                    reason: format!(
+                        "{version} is outside {} required on branch {}",
+                        constraint.requirement, constraint.branch
+                    ),
+                }
+            }
+            _ => tag_match,
+        }
     }
 
-    fn default_version() -> Version {
-        Version::new(0, 0, 1)
+    fn tag_list(&self) -> Vec<String> {
+        let tags = GitTags::new();
+        let repo = &self.project.repo;
+        let tag_list = if self.project.config.repository.reachable_tags_only {
+            tags.get_reachable_tags(repo)
+        } else {
+            tags.get_tags(repo)
+        };
+        tag_list.unwrap_or_default()
+    }
+
+    /// Matchers for the primary tag pattern followed by any legacy tag patterns.
+    fn tag_matchers(config: &Config) -> Result<Vec<TagMatcher>, SheepError> {
+        let repo_config = &config.repository;
+        std::iter::once(&repo_config.tag_pattern)
+            .chain(repo_config.legacy_tag_patterns.iter())
+            .map(|tag_pattern| TagMatcher::new(tag_pattern))
+            .collect()
     }
 }
 
 /// Manually created mock for [`ProjectVersion`]. Automock was having trouble with the lifetime
 /// generic in ProjectVersion.
 #[cfg(test)]
-pub struct MockProjectVersion;
+pub struct MockProjectVersion {
+    latest_version: Option<Version>,
+}
 
 #[cfg(test)]
 impl MockProjectVersion {
     pub fn mock() -> Self {
-        Self {}
+        Self { latest_version: Some(Version::new(1, 0, 0)) }
+    }
+
+    pub fn mock_without_tags() -> Self {
+        Self { latest_version: None }
     }
 
-    pub fn new(_: &Project) -> Self {
-        Self {}
+    pub fn new(_: &Project, _: Option<&VersionConstraint>) -> Result<Self, SheepError> {
+        Ok(Self::mock())
     }
 
     pub fn current_version(&self) -> Version {
-        Version::new(1, 0, 0)
+        self.latest_version().unwrap_or_else(|| self.initial_version())
     }
-}
\ No newline at end of file
+
+    pub fn latest_version(&self) -> Option<Version> {
+        self.latest_version.clone()
+    }
+
+    pub fn initial_version(&self) -> Version {
+        Version::new(0, 0, 1)
+    }
+
+    pub fn latest_tag(&self) -> Option<String> {
+        self.latest_version.as_ref().map(Version::to_string)
+    }
+
+    pub fn tag_matches(&self) -> Vec<(String, TagMatch)> {
+        vec![]
+    }
+}
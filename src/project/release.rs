@@ -0,0 +1,129 @@
+use crate::config::TransformConfig;
+use crate::project::graph::PackageGraph;
+use crate::version::update::VersionUpdate;
+use crate::SheepError;
+use std::fmt::{Display, Formatter};
+
+/// The packages to release, ordered so dependencies are released before their dependents.
+#[derive(Debug, PartialEq)]
+pub struct ReleasePlan {
+    pub steps: Vec<ReleaseStep>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReleaseStep {
+    pub package: String,
+    /// True when the package was selected for release, false when it is released because one
+    /// of its dependencies is.
+    pub requested: bool,
+    /// The dependencies of this package which are released earlier in the plan.
+    pub released_dependencies: Vec<String>,
+}
+
+impl ReleasePlan {
+    pub fn new(graph: &PackageGraph, requested: &[String]) -> Result<ReleasePlan, SheepError> {
+        let packages = graph.with_dependents(requested);
+        let steps = graph
+            .topological_order(&packages)?
+            .into_iter()
+            .map(|package| ReleaseStep {
+                requested: requested.contains(&package),
+                released_dependencies: graph
+                    .dependencies(&package)
+                    .into_iter()
+                    .filter(|dependency| packages.contains(dependency))
+                    .collect(),
+                package,
+            })
+            .collect();
+        Ok(ReleasePlan { steps })
+    }
+}
+
+impl Display for ReleasePlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "🗺  release plan:")?;
+        for (index, step) in self.steps.iter().enumerate() {
+            let number = index + 1;
+            if step.requested {
+                write!(f, "  {number}. {}", step.package)?;
+            } else {
+                let dependencies = step.released_dependencies.join(", ");
+                write!(f, "  {number}. {} (patch, depends on {dependencies})", step.package)?;
+            }
+            if number < self.steps.len() {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A dependency released earlier in the plan, along with the transforms which update the
+/// dependent package's requirement on it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DependencyUpdate {
+    pub name: String,
+    pub transforms: Vec<TransformConfig>,
+    pub version_update: VersionUpdate,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{DependencyConfig, PackageConfig};
+    use crate::project::graph::PackageGraph;
+    use crate::project::release::{ReleasePlan, ReleaseStep};
+
+    #[test]
+    fn new_cascades_to_dependents() {
+        let graph = PackageGraph::from_config(&packages()).unwrap();
+        let plan = ReleasePlan::new(&graph, &["core".to_string()]).unwrap();
+        let expected = ReleasePlan {
+            steps: vec![
+                ReleaseStep {
+                    package: "core".to_string(),
+                    requested: true,
+                    released_dependencies: vec![],
+                },
+                ReleaseStep {
+                    package: "api".to_string(),
+                    requested: false,
+                    released_dependencies: vec!["core".to_string()],
+                },
+            ],
+        };
+        assert_eq!(expected, plan)
+    }
+
+    #[test]
+    fn display() {
+        let graph = PackageGraph::from_config(&packages()).unwrap();
+        let plan = ReleasePlan::new(&graph, &["core".to_string()]).unwrap();
+        let expected = "🗺  release plan:\n  1. core\n  2. api (patch, depends on core)";
+        assert_eq!(expected, plan.to_string())
+    }
+
+    fn packages() -> Vec<PackageConfig> {
+        vec![
+            PackageConfig {
+                name: "core".to_string(),
+                path: "core".to_string(),
+                ..Default::default()
+            },
+            PackageConfig {
+                name: "api".to_string(),
+                path: "api".to_string(),
+                dependencies: vec![DependencyConfig {
+                    name: "core".to_string(),
+                    transforms: vec![],
+                }],
+                ..Default::default()
+            },
+            PackageConfig {
+                name: "docs".to_string(),
+                path: "docs".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+}
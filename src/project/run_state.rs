@@ -0,0 +1,218 @@
+use crate::version::update::VersionUpdate;
+use crate::SheepError;
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Progress of a release with subprojects, persisted so an interrupted release can be resumed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RunState {
+    pub current_version: Version,
+    pub next_version: Version,
+    #[serde(default)]
+    pub subprojects: BTreeMap<String, SubprojectRecord>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SubprojectRecord {
+    pub status: SubprojectStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubprojectStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+impl RunState {
+    pub fn new(version_update: &VersionUpdate) -> Self {
+        RunState {
+            current_version: version_update.current_version.clone(),
+            next_version: version_update.next_version.clone(),
+            subprojects: BTreeMap::new(),
+        }
+    }
+
+    /// The location of the run state within the repository's `.git` directory. Each package
+    /// of a multi-package repository has its own run state.
+    pub fn path<P: AsRef<Path>>(git_dir: P, package: Option<&str>) -> PathBuf {
+        let file_name = match package {
+            Some(package) => format!("run-state-{package}.toml"),
+            None => "run-state.toml".to_string(),
+        };
+        git_dir.as_ref().join("sheepit").join(file_name)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<RunState>, SheepError> {
+        load(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SheepError> {
+        save(self, path)
+    }
+
+    /// Removes the run state once a release has fully completed.
+    pub fn remove<P: AsRef<Path>>(path: P) -> Result<(), SheepError> {
+        remove(path)
+    }
+
+    pub fn version_update(&self) -> VersionUpdate {
+        VersionUpdate {
+            current_version: self.current_version.clone(),
+            next_version: self.next_version.clone(),
+        }
+    }
+
+    pub fn is_completed(&self, subproject: &str) -> bool {
+        self.subprojects
+            .get(subproject)
+            .map(|record| record.status == SubprojectStatus::Completed)
+            .unwrap_or(false)
+    }
+
+    pub fn set_status(&mut self, subproject: &str, status: SubprojectStatus, error: Option<String>) {
+        let record = SubprojectRecord { status, error };
+        self.subprojects.insert(subproject.to_string(), record);
+    }
+}
+
+/// The packages of a multi-package release which were fully released, persisted so a resumed
+/// release skips them.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PlanState {
+    #[serde(default)]
+    pub released: BTreeMap<String, ReleasedPackage>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReleasedPackage {
+    pub current_version: Version,
+    pub next_version: Version,
+}
+
+impl PlanState {
+    /// The location of the plan state within the repository's `.git` directory.
+    pub fn path<P: AsRef<Path>>(git_dir: P) -> PathBuf {
+        git_dir.as_ref().join("sheepit").join("plan-state.toml")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<PlanState>, SheepError> {
+        load(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SheepError> {
+        save(self, path)
+    }
+
+    /// Removes the plan state once every package has been released.
+    pub fn remove<P: AsRef<Path>>(path: P) -> Result<(), SheepError> {
+        remove(path)
+    }
+
+    pub fn released_version(&self, package: &str) -> Option<VersionUpdate> {
+        let released = self.released.get(package)?;
+        Some(VersionUpdate {
+            current_version: released.current_version.clone(),
+            next_version: released.next_version.clone(),
+        })
+    }
+
+    pub fn set_released(&mut self, package: &str, version_update: &VersionUpdate) {
+        let released = ReleasedPackage {
+            current_version: version_update.current_version.clone(),
+            next_version: version_update.next_version.clone(),
+        };
+        self.released.insert(package.to_string(), released);
+    }
+}
+
+fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<Option<T>, SheepError> {
+    if !path.as_ref().exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path)?;
+    Ok(Some(toml::from_str(&text)?))
+}
+
+fn save<T: Serialize, P: AsRef<Path>>(state: &T, path: P) -> Result<(), SheepError> {
+    if let Some(parent) = path.as_ref().parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let text = toml::to_string(state).map_err(|error| {
+        let message = format!("failed to write {}", path.as_ref().to_string_lossy());
+        SheepError::io(&message).with_source(error)
+    })?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+fn remove<P: AsRef<Path>>(path: P) -> Result<(), SheepError> {
+    if path.as_ref().exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::project::run_state::{PlanState, RunState, SubprojectStatus};
+    use crate::version::update::VersionUpdate;
+    use tempfile::TempDir;
+
+    #[test]
+    fn load_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = RunState::path(temp_dir.path(), None);
+        assert_eq!(None, RunState::load(path).expect("failed to load"))
+    }
+
+    #[test]
+    fn save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = RunState::path(temp_dir.path(), None);
+        let mut run_state = RunState::new(&VersionUpdate::new("1.0.0", "1.1.0"));
+        run_state.set_status("sdk-ios", SubprojectStatus::Completed, None);
+        run_state.set_status("sdk-android", SubprojectStatus::Failed, Some("oops".to_string()));
+
+        run_state.save(&path).expect("failed to save");
+        let loaded = RunState::load(&path).expect("failed to load");
+        assert_eq!(Some(run_state), loaded)
+    }
+
+    #[test]
+    fn path_per_package() {
+        let path = RunState::path("/repo/.git", Some("api"));
+        assert!(path.ends_with("sheepit/run-state-api.toml"))
+    }
+
+    #[test]
+    fn plan_state_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = PlanState::path(temp_dir.path());
+        let mut plan_state = PlanState::default();
+        plan_state.set_released("core", &VersionUpdate::new("1.0.0", "1.0.1"));
+
+        plan_state.save(&path).expect("failed to save");
+        let loaded = PlanState::load(&path).expect("failed to load").unwrap();
+        assert_eq!(Some(VersionUpdate::new("1.0.0", "1.0.1")), loaded.released_version("core"));
+        assert_eq!(None, loaded.released_version("api"))
+    }
+
+    #[test]
+    fn is_completed() {
+        let mut run_state = RunState::new(&VersionUpdate::new("1.0.0", "1.1.0"));
+        run_state.set_status("sdk-ios", SubprojectStatus::Completed, None);
+        run_state.set_status("sdk-android", SubprojectStatus::Failed, Some("oops".to_string()));
+
+        assert!(run_state.is_completed("sdk-ios"));
+        assert!(!run_state.is_completed("sdk-android"));
+        assert!(!run_state.is_completed("sdk-web"));
+    }
+}
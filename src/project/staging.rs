@@ -0,0 +1,191 @@
+use crate::SheepError;
+use glob::Pattern;
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Decides which of the repository's changed files belong in the release commit. Files changed by
+/// transforms are committed along with files matching an include glob, unless they match an
+/// exclude glob.
+#[derive(Debug)]
+pub struct CommitStaging {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    scope: Option<PathBuf>,
+}
+
+impl CommitStaging {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<CommitStaging, SheepError> {
+        let staging = CommitStaging {
+            include: patterns(include)?,
+            exclude: patterns(exclude)?,
+            scope: None,
+        };
+        Ok(staging)
+    }
+
+    /// Only considers changes within `scope`, such as a package's directory. Transformed paths
+    /// are committed wherever they are.
+    pub fn with_scope(self, scope: &str) -> CommitStaging {
+        CommitStaging {
+            scope: Some(PathBuf::from(scope)),
+            ..self
+        }
+    }
+
+    /// Returns the changed paths to commit. Fails if a changed path was neither transformed,
+    /// included nor excluded, since it would otherwise be silently left out of the release.
+    /// Transformed paths are normalized first, since configs may write them as `./Cargo.toml`
+    /// or `packages/api/../shared/Cargo.toml` while changed paths come from git.
+    pub fn commit_paths(&self,
+                        changed_paths: &[PathBuf],
+                        transformed_paths: &BTreeSet<PathBuf>) -> Result<Vec<PathBuf>, SheepError> {
+        let transformed_paths = normalize_all(transformed_paths);
+        let mut commit_paths = vec![];
+        let mut unexpected_paths = vec![];
+        for path in changed_paths {
+            let transformed = transformed_paths.contains(path);
+            if !transformed && !self.is_in_scope(path) || matches_any(&self.exclude, path) {
+                continue;
+            }
+            if transformed || matches_any(&self.include, path) {
+                commit_paths.push(path.clone());
+            } else {
+                unexpected_paths.push(path.to_string_lossy().to_string());
+            }
+        }
+        if !unexpected_paths.is_empty() {
+            let message = format!(
+                "unexpected changes to {}, add them to commit_include or commit_exclude",
+                unexpected_paths.join(", ")
+            );
+            return Err(SheepError::validation(&message));
+        }
+        Ok(commit_paths)
+    }
+
+    /// Returns the changed paths which are left out of the commit because they're outside the
+    /// scope and weren't transformed.
+    pub fn out_of_scope_paths(&self,
+                              changed_paths: &[PathBuf],
+                              transformed_paths: &BTreeSet<PathBuf>) -> Vec<PathBuf> {
+        let transformed_paths = normalize_all(transformed_paths);
+        changed_paths
+            .iter()
+            .filter(|path| !self.is_in_scope(path) && !transformed_paths.contains(*path))
+            .cloned()
+            .collect()
+    }
+
+    fn is_in_scope(&self, path: &Path) -> bool {
+        match &self.scope {
+            Some(scope) => path.starts_with(scope),
+            None => true,
+        }
+    }
+}
+
+fn patterns(globs: &[String]) -> Result<Vec<Pattern>, SheepError> {
+    globs.iter()
+        .map(|glob| Pattern::new(glob).map_err(SheepError::from))
+        .collect()
+}
+
+fn normalize_all(paths: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+    paths.iter().map(|path| normalize(path)).collect()
+}
+
+/// Removes `.` components and resolves `..` components against the components before them.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn matches_any(patterns: &[Pattern], path: &Path) -> bool {
+    patterns.iter().any(|pattern| pattern.matches_path(path))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::project::staging::CommitStaging;
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    #[test]
+    fn commit_paths_transformed_and_included() {
+        let staging = CommitStaging::new(&strings(&["dist/*"]), &strings(&["*.log"])).unwrap();
+        let changed = paths(&["Cargo.toml", "dist/app.js", "build.log"]);
+        let transformed = set(&["Cargo.toml"]);
+
+        let result = staging.commit_paths(&changed, &transformed).unwrap();
+        assert_eq!(paths(&["Cargo.toml", "dist/app.js"]), result)
+    }
+
+    #[test]
+    fn commit_paths_unexpected_change() {
+        let staging = CommitStaging::new(&[], &[]).unwrap();
+        let changed = paths(&["Cargo.toml", ".env"]);
+        let transformed = set(&["Cargo.toml"]);
+
+        let error = staging.commit_paths(&changed, &transformed).expect_err("should fail");
+        assert!(error.message().contains(".env"))
+    }
+
+    #[test]
+    fn commit_paths_outside_scope() {
+        let staging = CommitStaging::new(&[], &[]).unwrap().with_scope("packages/api");
+        let changed = paths(&["packages/api/Cargo.toml", "packages/web/package.json"]);
+        let transformed = set(&["packages/api/Cargo.toml"]);
+
+        let result = staging.commit_paths(&changed, &transformed).unwrap();
+        assert_eq!(paths(&["packages/api/Cargo.toml"]), result);
+        let out_of_scope = staging.out_of_scope_paths(&changed, &transformed);
+        assert_eq!(paths(&["packages/web/package.json"]), out_of_scope)
+    }
+
+    #[test]
+    fn commit_paths_transformed_outside_scope() {
+        let staging = CommitStaging::new(&[], &[]).unwrap().with_scope("packages/api");
+        let changed = paths(&["Cargo.lock", "packages/api/Cargo.toml"]);
+        let transformed = set(&["./Cargo.lock", "packages/api/Cargo.toml"]);
+
+        let result = staging.commit_paths(&changed, &transformed).unwrap();
+        assert_eq!(changed, result);
+        assert!(staging.out_of_scope_paths(&changed, &transformed).is_empty())
+    }
+
+    #[test]
+    fn commit_paths_normalizes_transformed_paths() {
+        let staging = CommitStaging::new(&[], &[]).unwrap().with_scope("packages/api");
+        let changed = paths(&["packages/api/Cargo.toml", "packages/api/VERSION"]);
+        let transformed = set(&["./packages/api/Cargo.toml", "packages/api/src/../VERSION"]);
+
+        let result = staging.commit_paths(&changed, &transformed).unwrap();
+        assert_eq!(changed, result)
+    }
+
+    #[test]
+    fn new_invalid_glob() {
+        CommitStaging::new(&strings(&["[abc"]), &[]).expect_err("glob should be invalid");
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn paths(values: &[&str]) -> Vec<PathBuf> {
+        values.iter().map(PathBuf::from).collect()
+    }
+
+    fn set(values: &[&str]) -> BTreeSet<PathBuf> {
+        paths(values).into_iter().collect()
+    }
+}
@@ -18,7 +18,7 @@ impl ProjectStrings {
         ProjectStrings {
             branch_name: repo_config.branch_pattern.replace(token, next_version),
             commit_message: repo_config.commit_message.replace(token, next_version),
-            remote_name: "origin".to_string(),
+            remote_name: repo_config.remote_name.clone(),
             tag_name: repo_config.tag_pattern.replace(token, next_version),
         }
     }
@@ -0,0 +1,570 @@
+use crate::config::{Config, SubprojectConfig};
+use crate::error::SheepError;
+use crate::project::operation::Operation;
+use crate::project::run_state::{RunState, SubprojectStatus};
+use crate::project::{temp, Project};
+use crate::repo::clone::GitCloner;
+use crate::repo::open::GitOpener;
+use crate::repo::path;
+use crate::repo::status::GitStatuses;
+use crate::transform::project_transform::ProjectTransformer;
+use crate::version::update::VersionUpdate;
+use git2::Repository;
+use semver::Version;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// Controls how a project's subprojects are released.
+#[derive(Clone, Debug, Default)]
+pub struct SubprojectOptions {
+    /// The number of subprojects released at the same time. Zero is treated as one.
+    pub jobs: usize,
+    /// How many levels of nested subprojects are released, `None` for no limit.
+    pub max_depth: Option<usize>,
+    /// When true, subprojects completed by a previous run of the same release are skipped.
+    pub resume: bool,
+    /// The projects above this one, used to detect subprojects which reference each other.
+    ancestors: Vec<String>,
+    depth: usize,
+    /// The url, or local path, of the project which released this subproject.
+    parent_url: Option<String>,
+    /// The ssh key of the project which released this subproject, used when cloning it.
+    ssh_key_path: Option<String>,
+    /// Subprojects released so far anywhere in the release, shared so diamonds release once.
+    releases: Arc<SharedReleases>,
+}
+
+/// The outcome of releasing a subproject which may be reached through more than one parent.
+#[derive(Clone, Debug, PartialEq)]
+enum SharedRelease {
+    InProgress,
+    Completed,
+    /// The release failed with the given report.
+    Failed(String),
+}
+
+/// Subprojects released anywhere in the release, keyed by [`subproject_key`]. The first path to
+/// reach a subproject releases it, while later paths wait for and share its outcome.
+#[derive(Debug, Default)]
+struct SharedReleases {
+    releases: Mutex<BTreeMap<String, SharedRelease>>,
+    finished: Condvar,
+}
+
+impl SharedReleases {
+    /// Claims the subproject's release, returning `None` if the caller should release it.
+    /// Otherwise waits for the release already claimed to finish and returns its outcome.
+    fn claim(&self, key: &str) -> Option<SharedRelease> {
+        let mut releases = self.releases.lock().expect("releases lock poisoned");
+        loop {
+            match releases.get(key) {
+                None => {
+                    releases.insert(key.to_string(), SharedRelease::InProgress);
+                    return None;
+                }
+                Some(SharedRelease::InProgress) => {
+                    releases = self.finished.wait(releases).expect("releases lock poisoned");
+                }
+                Some(release) => return Some(release.clone()),
+            }
+        }
+    }
+
+    fn finish(&self, key: &str, release: SharedRelease) {
+        let mut releases = self.releases.lock().expect("releases lock poisoned");
+        releases.insert(key.to_string(), release);
+        self.finished.notify_all();
+    }
+}
+
+impl SubprojectOptions {
+    pub fn new(jobs: usize, max_depth: Option<usize>, resume: bool) -> Self {
+        SubprojectOptions {
+            jobs,
+            max_depth,
+            resume,
+            ..Default::default()
+        }
+    }
+
+    /// How deeply nested the project is, zero for the project sheepit was run on.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn parent_url(&self) -> Option<&str> {
+        self.parent_url.as_deref()
+    }
+}
+
+impl Project {
+    pub(super) fn update_subprojects(
+        &self,
+        version_update: &VersionUpdate,
+        run_state: Option<RunState>,
+    ) -> Result<(), SheepError> {
+        let (configs, disabled): (Vec<&SubprojectConfig>, Vec<&SubprojectConfig>) =
+            self.config.subprojects.iter().partition(|config| config.enabled);
+        for config in &disabled {
+            println!("⏸  skipping disabled subproject {}", config.name());
+        }
+        if configs.is_empty() {
+            return Ok(());
+        }
+        let options = &self.subproject_options;
+        if self.is_beyond_max_depth() {
+            println!("⏹  skipping subprojects beyond depth {}", options.depth);
+            return Ok(());
+        }
+        let ancestors = self.subproject_ancestors();
+
+        let state_path = self.run_state_path();
+        let run_state = run_state.unwrap_or_else(|| RunState::new(version_update));
+        run_state.save(&state_path)?;
+
+        let (skipped, pending): (Vec<&SubprojectConfig>, Vec<&SubprojectConfig>) = configs
+            .iter()
+            .copied()
+            .partition(|config| run_state.is_completed(config.name()));
+        for config in &skipped {
+            println!("⏭  skipping completed subproject {}", config.name());
+        }
+
+        let run_state = Mutex::new(run_state);
+        let queue = Mutex::new(pending.into_iter());
+        let jobs = options.jobs.max(1);
+        // Repositories can't be shared between threads, so workers only borrow plain options
+        let is_dry_run = self.is_dry_run_project;
+        let repo_config = &self.config.repository;
+        let parent_url = self
+            .remotes()
+            .remote_url(&self.repo, &repo_config.remote_name)
+            .unwrap_or_else(|_| self.repo_path().to_string_lossy().to_string());
+        let options = SubprojectOptions {
+            ancestors,
+            depth: options.depth + 1,
+            parent_url: Some(parent_url),
+            resume: false,
+            ssh_key_path: repo_config.ssh_key_path.clone(),
+            ..options.clone()
+        };
+        let save_status = |name: &str, status, error| -> Result<(), SheepError> {
+            let mut state = run_state.lock().expect("run state lock poisoned");
+            state.set_status(name, status, error);
+            state.save(&state_path)
+        };
+
+        let results: Vec<Result<(), SheepError>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs)
+                .map(|_| {
+                    scope.spawn(|| -> Result<(), SheepError> {
+                        loop {
+                            let next = queue.lock().expect("queue lock poisoned").next();
+                            let Some(config) = next else {
+                                return Ok(());
+                            };
+                            let name = config.name();
+                            let key = subproject_key(config);
+                            match options.releases.claim(&key) {
+                                Some(SharedRelease::Failed(report)) => {
+                                    println!("⏭  subproject {name} already failed to release");
+                                    let message = format!("shared release failed: {report}");
+                                    save_status(name, SubprojectStatus::Failed, Some(message))?;
+                                    continue;
+                                }
+                                Some(_) => {
+                                    println!("⏭  subproject {name} was already released");
+                                    save_status(name, SubprojectStatus::Completed, None)?;
+                                    continue;
+                                }
+                                None => {}
+                            }
+                            save_status(name, SubprojectStatus::Pending, None)?;
+                            let result =
+                                Self::update_subproject(config, version_update, is_dry_run, &options);
+                            let release = match &result {
+                                Ok(_) => SharedRelease::Completed,
+                                Err(error) => SharedRelease::Failed(error.report().to_string()),
+                            };
+                            options.releases.finish(&key, release.clone());
+                            match release {
+                                SharedRelease::Failed(message) => {
+                                    save_status(name, SubprojectStatus::Failed, Some(message))?
+                                }
+                                _ => save_status(name, SubprojectStatus::Completed, None)?,
+                            }
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("subproject worker panicked"))
+                .collect()
+        });
+        results.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+        let run_state = run_state.into_inner().expect("run state lock poisoned");
+        Self::print_subproject_summary(&configs, &skipped, &run_state);
+
+        let failed_count = configs
+            .iter()
+            .filter(|config| !run_state.is_completed(config.name()))
+            .count();
+        if failed_count > 0 {
+            let message = format!(
+                "{failed_count} subproject(s) failed, re-run with --resume to retry them"
+            );
+            return Err(SheepError::subproject(&message));
+        }
+        RunState::remove(&state_path)?;
+        Ok(())
+    }
+
+    /// Fails if a subproject is this project or one of the projects above it. This is checked
+    /// before this project is updated, though the projects above it have already been released
+    /// by then, since a subproject's own subprojects are only known once it has been cloned.
+    pub(super) fn check_subproject_cycles(&self) -> Result<(), SheepError> {
+        if self.is_beyond_max_depth() {
+            return Ok(());
+        }
+        let ancestors = self.subproject_ancestors();
+        for config in self.config.subprojects.iter().filter(|config| config.enabled) {
+            let key = subproject_key(config);
+            if ancestors.contains(&key) {
+                let cycle = ancestors
+                    .iter()
+                    .skip_while(|ancestor| **ancestor != key)
+                    .chain([&key])
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                let message = format!("subproject cycle detected: {cycle}");
+                return Err(SheepError::config(&message));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_beyond_max_depth(&self) -> bool {
+        let options = &self.subproject_options;
+        options.max_depth.is_some_and(|max_depth| options.depth >= max_depth)
+    }
+
+    /// Identifies this project and the projects above it. A project is identified by its remote
+    /// url as well as its location on disk, matching how subprojects are configured.
+    fn subproject_ancestors(&self) -> Vec<String> {
+        let mut ancestors = self.subproject_options.ancestors.clone();
+        let remote_name = &self.config.repository.remote_name;
+        if let Ok(url) = self.remotes().remote_url(&self.repo, remote_name) {
+            ancestors.push(path::normalized_url(&url));
+        }
+        ancestors.push(local_key(&self.repo_path()));
+        ancestors
+    }
+
+    fn update_subproject(
+        config: &SubprojectConfig,
+        version_update: &VersionUpdate,
+        is_dry_run: bool,
+        options: &SubprojectOptions,
+    ) -> Result<VersionUpdate, SheepError> {
+        let operation = subproject_operation(config, version_update)?;
+        let mut project = Self::new_subproject(config, is_dry_run, options)?;
+        project.subproject_options = options.clone();
+        println!("------------");
+        println!("🚢 sheep'n subproject {}", config.name());
+        project.update(operation)
+    }
+
+    /// Opens the subproject, checking out its configured branch and opening its configured
+    /// config file. The branch replaces the subproject's default branch so commits are pushed to it.
+    /// A tag or commit is checked out detached, so a release commit needs a release branch.
+    fn new_subproject(
+        subproject: &SubprojectConfig,
+        is_dry_run_project: bool,
+        options: &SubprojectOptions,
+    ) -> Result<Project, SheepError> {
+        let ssh_key_path = options.ssh_key_path.as_deref();
+        let has_url = !subproject.repo_url.is_empty();
+        let (repo, repo_path) = match &subproject.path {
+            Some(_) if has_url => {
+                let message = format!(
+                    "subproject {} can't set both path and repo_url",
+                    subproject.name()
+                );
+                return Err(SheepError::config(&message));
+            }
+            None if !has_url => {
+                return Err(SheepError::config("subproject must set either path or repo_url"));
+            }
+            // Dry runs never touch the checked out repo, a copy is released instead
+            Some(local_path) if is_dry_run_project => {
+                let repo_name = Path::new(local_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "subproject".to_string());
+                Self::clone_subproject(subproject, local_path, &repo_name, ssh_key_path)?
+            }
+            Some(local_path) => Self::open_local_subproject(subproject, local_path)?,
+            None => {
+                let url = &subproject.repo_url;
+                let repo_name = path::repo_name(url)?;
+                Self::clone_subproject(subproject, url, &repo_name, ssh_key_path)?
+            }
+        };
+        let config_path = subproject.config_path.as_ref().map(|path| repo_path.join(path));
+        let mut config = Config::open(&repo_path, config_path.as_deref())?;
+        if let Some(branch) = &subproject.branch {
+            let repo_config = &mut config.repository;
+            if !repo.head_detached()? {
+                repo_config.default_branch = branch.clone();
+            } else if repo_config.enable_commit && !repo_config.enable_branch {
+                let message = format!(
+                    "subproject {} checks out {branch}, which isn't a branch, so it needs \
+                    enable_branch to commit a release",
+                    subproject.name()
+                );
+                return Err(SheepError::config(&message));
+            }
+        }
+        let project = Project {
+            config,
+            repo,
+            transformer: ProjectTransformer::new(&repo_path),
+            is_dry_run_project,
+            package: None,
+            dependency_updates: vec![],
+            subproject_options: SubprojectOptions::default(),
+        };
+        Ok(project)
+    }
+
+    fn clone_subproject(
+        subproject: &SubprojectConfig,
+        url: &str,
+        repo_name: &str,
+        ssh_key_path: Option<&str>,
+    ) -> Result<(Repository, PathBuf), SheepError> {
+        // Each subproject gets its own directory so subprojects sharing a name can't collide
+        let repo_path = temp::directory()?.join(repo_name);
+        let repo = GitCloner::new()
+            .with_branch(subproject.branch.as_deref())
+            .with_ssh_key_path(ssh_key_path)
+            .clone(url, &repo_path)?;
+        Ok((repo, repo_path))
+    }
+
+    /// Opens a subproject which is already checked out. It must be clean, and on the configured
+    /// branch if there is one, since it is released in place.
+    fn open_local_subproject(
+        subproject: &SubprojectConfig,
+        local_path: &str,
+    ) -> Result<(Repository, PathBuf), SheepError> {
+        let repo = GitOpener::new().open(local_path)?;
+        let dirty_paths = GitStatuses::new().dirty_paths(&repo)?;
+        if !dirty_paths.is_empty() {
+            let paths = dirty_paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let message = format!("subproject {local_path} has uncommitted changes: {paths}");
+            return Err(SheepError::validation(&message));
+        }
+        if let Some(branch) = &subproject.branch {
+            let head = repo.head()?;
+            let current_branch = head.shorthand().unwrap_or_default();
+            let head_commit = head.peel_to_commit()?.id();
+            let on_ref = repo.head_detached()?
+                && repo.revparse_single(branch)?.peel_to_commit()?.id() == head_commit;
+            if current_branch != branch && !on_ref {
+                let message =
+                    format!("subproject {local_path} is on {current_branch}, expected {branch}");
+                return Err(SheepError::validation(&message));
+            }
+        }
+        Ok((repo, PathBuf::from(local_path)))
+    }
+
+    fn print_subproject_summary(
+        configs: &[&SubprojectConfig],
+        skipped: &[&SubprojectConfig],
+        run_state: &RunState,
+    ) {
+        println!("------------");
+        println!("📋 subproject summary:");
+        for config in configs {
+            let name = config.name();
+            let record = run_state.subprojects.get(name);
+            let was_skipped = skipped.iter().any(|skipped| skipped.name() == name);
+            match record.map(|record| (record.status, &record.error)) {
+                _ if was_skipped => println!("  ⏭  skipped    {name}"),
+                Some((SubprojectStatus::Completed, _)) => println!("  ✅ completed  {name}"),
+                Some((SubprojectStatus::Failed, Some(error))) => {
+                    println!("  ❌ failed     {name}: {error}")
+                }
+                _ => println!("  ❌ failed     {name}"),
+            }
+        }
+    }
+}
+
+/// Identifies a subproject so the same repository matches wherever it is referenced.
+fn subproject_key(config: &SubprojectConfig) -> String {
+    match &config.path {
+        Some(local_path) => local_key(Path::new(local_path)),
+        None => path::normalized_url(&config.repo_url),
+    }
+}
+
+fn local_key(local_path: &Path) -> String {
+    fs::canonicalize(local_path)
+        .unwrap_or_else(|_| local_path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Chooses how the subproject's version follows the project's version update.
+fn subproject_operation(
+    config: &SubprojectConfig,
+    version_update: &VersionUpdate,
+) -> Result<Operation, SheepError> {
+    match (&config.bump, &config.version_map) {
+        (Some(_), Some(_)) => {
+            let message = format!(
+                "subproject {} can't set both bump and version_map",
+                config.name()
+            );
+            Err(SheepError::config(&message))
+        }
+        (Some(bump_mode), None) => Ok(Operation::BumpVersion(bump_mode.clone())),
+        (None, Some(template)) => Ok(Operation::SetVersion {
+            current_version: None,
+            next_version: map_version(template, &version_update.next_version)?,
+        }),
+        (None, None) => Ok(Operation::SetVersion {
+            current_version: Some(version_update.current_version.clone()),
+            next_version: version_update.next_version.clone(),
+        }),
+    }
+}
+
+/// Renders a version template such as `{major}.{minor}.0` with the parts of `version`.
+fn map_version(template: &str, version: &Version) -> Result<Version, SheepError> {
+    let text = template
+        .replace("{major}", &version.major.to_string())
+        .replace("{minor}", &version.minor.to_string())
+        .replace("{patch}", &version.patch.to_string())
+        .replace("{pre}", version.pre.as_str());
+    Version::parse(&text).map_err(|error| {
+        let message = format!("version_map {template} produced invalid version {text}: {error}");
+        SheepError::config(&message)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::SubprojectConfig;
+    use crate::project::operation::{BumpMode, Operation};
+    use crate::project::subproject::{
+        map_version, subproject_key, subproject_operation, SharedRelease, SharedReleases,
+    };
+    use crate::version::update::VersionUpdate;
+    use semver::Version;
+    use std::thread;
+
+    #[test]
+    fn map_version_template() {
+        let version = Version::new(2, 3, 4);
+        let mapped = map_version("{major}.{minor}.0", &version).unwrap();
+        assert_eq!(Version::new(2, 3, 0), mapped)
+    }
+
+    #[test]
+    fn map_version_invalid() {
+        let version = Version::new(2, 3, 4);
+        map_version("{major}.{minor}", &version).expect_err("version should be invalid");
+    }
+
+    #[test]
+    fn subproject_operation_same_version() {
+        let config = SubprojectConfig::default();
+        let operation = subproject_operation(&config, &version_update()).unwrap();
+        assert!(matches!(
+            operation,
+            Operation::SetVersion { current_version: Some(current), next_version }
+                if current == Version::new(1, 2, 3) && next_version == Version::new(1, 3, 0)
+        ))
+    }
+
+    #[test]
+    fn subproject_operation_bump() {
+        let config = SubprojectConfig {
+            bump: Some(BumpMode::Patch),
+            ..Default::default()
+        };
+        let operation = subproject_operation(&config, &version_update()).unwrap();
+        assert!(matches!(operation, Operation::BumpVersion(BumpMode::Patch)))
+    }
+
+    #[test]
+    fn subproject_operation_version_map() {
+        let config = SubprojectConfig {
+            version_map: Some("{major}.{minor}.0".to_string()),
+            ..Default::default()
+        };
+        let operation = subproject_operation(&config, &version_update()).unwrap();
+        assert!(matches!(
+            operation,
+            Operation::SetVersion { current_version: None, next_version }
+                if next_version == Version::new(1, 3, 0)
+        ))
+    }
+
+    #[test]
+    fn subproject_operation_bump_and_version_map() {
+        let config = SubprojectConfig {
+            bump: Some(BumpMode::Patch),
+            version_map: Some("{major}.{minor}.0".to_string()),
+            ..Default::default()
+        };
+        subproject_operation(&config, &version_update()).expect_err("should be ambiguous");
+    }
+
+    #[test]
+    fn subproject_key_normalizes_url() {
+        let config = SubprojectConfig {
+            repo_url: "git@github.com:some-user/sdk.git".to_string(),
+            ..Default::default()
+        };
+        assert_eq!("github.com/some-user/sdk", subproject_key(&config))
+    }
+
+    #[test]
+    fn shared_releases_first_claim_releases() {
+        let releases = SharedReleases::default();
+        assert_eq!(None, releases.claim("sdk"));
+        releases.finish("sdk", SharedRelease::Completed);
+        assert_eq!(Some(SharedRelease::Completed), releases.claim("sdk"))
+    }
+
+    #[test]
+    fn shared_releases_wait_for_outcome() {
+        let releases = SharedReleases::default();
+        assert_eq!(None, releases.claim("sdk"));
+        let outcome = thread::scope(|scope| {
+            let waiter = scope.spawn(|| releases.claim("sdk"));
+            releases.finish("sdk", SharedRelease::Failed("push failed".to_string()));
+            waiter.join().unwrap()
+        });
+        assert_eq!(Some(SharedRelease::Failed("push failed".to_string())), outcome)
+    }
+
+    fn version_update() -> VersionUpdate {
+        VersionUpdate::new("1.2.3", "1.3.0")
+    }
+}
@@ -1,8 +1,13 @@
 use std::path::PathBuf;
-use tempfile::Builder;
+use tempfile::{Builder, TempDir};
 use crate::error::SheepError;
 
 pub fn directory() -> Result<PathBuf, SheepError> {
     let temp = Builder::new().prefix("sheepit").tempdir()?;
     Ok(temp.into_path())
-}
\ No newline at end of file
+}
+
+/// A temporary directory which is deleted when it's dropped.
+pub fn scoped_directory() -> Result<TempDir, SheepError> {
+    Ok(Builder::new().prefix("sheepit").tempdir()?)
+}
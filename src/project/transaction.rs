@@ -0,0 +1,291 @@
+use crate::repo::branch::GitBranches;
+use crate::repo::commit;
+use crate::repo::remote::GitRemotes;
+use crate::repo::tag::GitTags;
+use crate::SheepError;
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A change made to the repository while releasing, recorded so it can be undone.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Mutation {
+    BranchCreated {
+        name: String,
+    },
+    /// HEAD moved away from `branch`, or from `commit` when HEAD was detached.
+    HeadMoved {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+        commit: String,
+    },
+    CommitCreated {
+        commit: String,
+        paths: Vec<PathBuf>,
+    },
+    TagCreated {
+        name: String,
+    },
+    BranchPushed {
+        name: String,
+        remote: String,
+    },
+    TagPushed {
+        name: String,
+        remote: String,
+    },
+}
+
+impl Mutation {
+    /// Records where HEAD is now, before it is moved.
+    pub fn head_moved(repo: &Repository) -> Result<Mutation, SheepError> {
+        let head = repo.head()?;
+        let branch = if head.is_branch() {
+            head.shorthand().map(String::from)
+        } else {
+            None
+        };
+        let commit = head.peel_to_commit()?.id().to_string();
+        Ok(Mutation::HeadMoved { branch, commit })
+    }
+}
+
+/// The mutations made by the last run, persisted as they happen so a failed or unwanted release
+/// can be rolled back, even from a later invocation.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TransactionLog {
+    #[serde(default)]
+    pub mutations: Vec<Mutation>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl TransactionLog {
+    /// The location of the log within the repository's `.git` directory.
+    pub fn path<P: AsRef<Path>>(git_dir: P) -> PathBuf {
+        git_dir.as_ref().join("sheepit").join("transaction-log.toml")
+    }
+
+    pub fn open<P: AsRef<Path>>(git_dir: P) -> Result<TransactionLog, SheepError> {
+        let path = TransactionLog::path(git_dir);
+        let mut log: TransactionLog = if path.exists() {
+            toml::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            TransactionLog::default()
+        };
+        log.path = path;
+        Ok(log)
+    }
+
+    /// Removes the log, which happens at the start of each run.
+    pub fn remove<P: AsRef<Path>>(git_dir: P) -> Result<(), SheepError> {
+        let path = TransactionLog::path(git_dir);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.mutations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+
+    pub fn record(&mut self, mutation: Mutation) -> Result<(), SheepError> {
+        self.mutations.push(mutation);
+        self.save()
+    }
+
+    /// Whether any ref was pushed after the first `since` mutations.
+    pub fn has_pushed_since(&self, since: usize) -> bool {
+        self.mutations.iter().skip(since).any(|mutation| {
+            matches!(mutation, Mutation::BranchPushed { .. } | Mutation::TagPushed { .. })
+        })
+    }
+
+    /// Undoes the mutations after the first `since`, newest first. Pushed refs are only deleted
+    /// from the remote when `remotes` are provided, otherwise they stay in the log so a later
+    /// `sheepit rollback --remote` can delete them. Each mutation is dropped from the log once
+    /// undone, so a rollback which fails part way through can be retried.
+    pub fn roll_back(&mut self,
+                     repo: &Repository,
+                     since: usize,
+                     remotes: Option<&GitRemotes>) -> Result<(), SheepError> {
+        let mut index = self.mutations.len();
+        while index > since {
+            index -= 1;
+            if undo(repo, &self.mutations[index], remotes)? {
+                self.mutations.remove(index);
+                self.save()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), SheepError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string(self).map_err(|error| {
+            let message = format!("failed to write {}", self.path.to_string_lossy());
+            SheepError::io(&message).with_source(error)
+        })?;
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
+
+/// Undoes a mutation, returning false when it was left in place.
+fn undo(repo: &Repository,
+        mutation: &Mutation,
+        remotes: Option<&GitRemotes>) -> Result<bool, SheepError> {
+    match (mutation, remotes) {
+        (Mutation::BranchCreated { name }, _) => {
+            println!("🗑  deleting branch {name}");
+            GitBranches::new().delete_branch(repo, name)?;
+        }
+        (Mutation::HeadMoved { branch: Some(branch), .. }, _) => {
+            println!("↩️  checking out {branch}");
+            GitBranches::new().checkout_branch(repo, branch)?;
+        }
+        (Mutation::HeadMoved { branch: None, commit }, _) => {
+            println!("↩️  checking out {commit}");
+            GitBranches::new().checkout_commit(repo, Oid::from_str(commit)?)?;
+        }
+        (Mutation::CommitCreated { commit, paths }, _) => {
+            println!("⏪ undoing commit {commit}");
+            commit::undo_commit(repo, Oid::from_str(commit)?, paths)?;
+        }
+        (Mutation::TagCreated { name }, _) => {
+            println!("🗑  deleting tag {name}");
+            GitTags::new().delete_tag(repo, name)?;
+        }
+        (Mutation::BranchPushed { name, remote: remote_name }, Some(remotes)) => {
+            println!("🗑  deleting branch {name} from {remote_name}");
+            remotes.delete_branch(repo, name, remote_name)?;
+        }
+        (Mutation::TagPushed { name, remote: remote_name }, Some(remotes)) => {
+            println!("🗑  deleting tag {name} from {remote_name}");
+            remotes.delete_tag(repo, name, remote_name)?;
+        }
+        (Mutation::BranchPushed { name, remote: remote_name }, None)
+        | (Mutation::TagPushed { name, remote: remote_name }, None) => {
+            println!("⚠️  {name} was already pushed to {remote_name}, leaving it in place");
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::project::transaction::{Mutation, TransactionLog};
+    use crate::repo::branch::GitBranches;
+    use crate::repo::tag::GitTags;
+    use crate::repo::testing;
+    use git2::BranchType;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn open_missing_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = TransactionLog::open(temp_dir.path()).expect("failed to open");
+        assert!(log.is_empty())
+    }
+
+    #[test]
+    fn record_and_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut log = TransactionLog::open(temp_dir.path()).unwrap();
+        let mutations = vec![
+            Mutation::BranchCreated { name: "release/1.1.0".to_string() },
+            Mutation::HeadMoved { branch: None, commit: "abc123".to_string() },
+            Mutation::CommitCreated {
+                commit: "def456".to_string(),
+                paths: vec![PathBuf::from("Cargo.toml")],
+            },
+            Mutation::TagPushed { name: "1.1.0".to_string(), remote: "origin".to_string() },
+        ];
+        for mutation in &mutations {
+            log.record(mutation.clone()).expect("failed to record");
+        }
+
+        let opened = TransactionLog::open(temp_dir.path()).expect("failed to open");
+        assert_eq!(mutations, opened.mutations)
+    }
+
+    #[test]
+    fn roll_back_local_changes() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let branches = GitBranches::new();
+        let tags = GitTags::new();
+        testing::write_file(&repo, "version.txt", "1.0.0");
+        let first = testing::commit(&repo, "first");
+        let original_branch = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        let mut log = TransactionLog::open(repo.path()).unwrap();
+        branches.create_branch(&repo, "release/1.1.0").unwrap();
+        log.record(Mutation::BranchCreated { name: "release/1.1.0".to_string() }).unwrap();
+        log.record(Mutation::head_moved(&repo).unwrap()).unwrap();
+        branches.checkout_branch(&repo, "release/1.1.0").unwrap();
+        testing::write_file(&repo, "version.txt", "1.1.0");
+        let commit = testing::commit(&repo, "preparing release 1.1.0");
+        let paths = vec![PathBuf::from("version.txt")];
+        log.record(Mutation::CommitCreated { commit: commit.to_string(), paths }).unwrap();
+        tags.create_tag(&repo, "1.1.0", None).unwrap();
+        log.record(Mutation::TagCreated { name: "1.1.0".to_string() }).unwrap();
+
+        log.roll_back(&repo, 0, None).expect("failed to roll back");
+
+        let head = repo.head().unwrap();
+        assert_eq!(Some(original_branch.as_str()), head.shorthand());
+        assert_eq!(first, head.peel_to_commit().unwrap().id());
+        assert!(repo.find_branch("release/1.1.0", BranchType::Local).is_err());
+        assert!(tags.get_tags(&repo).unwrap().is_empty());
+        assert!(TransactionLog::open(repo.path()).unwrap().is_empty())
+    }
+
+    #[test]
+    fn roll_back_since_keeps_earlier_mutations() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let tags = GitTags::new();
+        testing::commit(&repo, "first");
+
+        let mut log = TransactionLog::open(repo.path()).unwrap();
+        tags.create_tag(&repo, "api-1.0.0", None).unwrap();
+        log.record(Mutation::TagCreated { name: "api-1.0.0".to_string() }).unwrap();
+        tags.create_tag(&repo, "web-1.0.0", None).unwrap();
+        log.record(Mutation::TagCreated { name: "web-1.0.0".to_string() }).unwrap();
+
+        log.roll_back(&repo, 1, None).expect("failed to roll back");
+
+        assert_eq!(vec!["api-1.0.0".to_string()], tags.get_tags(&repo).unwrap());
+        assert_eq!(1, log.len())
+    }
+
+    #[test]
+    fn roll_back_keeps_pushed_refs_without_remotes() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let tags = GitTags::new();
+        testing::commit(&repo, "first");
+
+        let mut log = TransactionLog::open(repo.path()).unwrap();
+        tags.create_tag(&repo, "1.1.0", None).unwrap();
+        log.record(Mutation::TagCreated { name: "1.1.0".to_string() }).unwrap();
+        let pushed = Mutation::TagPushed { name: "1.1.0".to_string(), remote: "origin".to_string() };
+        log.record(pushed.clone()).unwrap();
+        assert!(log.has_pushed_since(0));
+
+        log.roll_back(&repo, 0, None).expect("failed to roll back");
+
+        assert!(tags.get_tags(&repo).unwrap().is_empty());
+        let opened = TransactionLog::open(repo.path()).unwrap();
+        assert_eq!(vec![pushed], opened.mutations)
+    }
+}
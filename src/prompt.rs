@@ -0,0 +1,51 @@
+use crate::SheepError;
+use std::io;
+use std::io::{BufRead, Write};
+
+/// Asks a yes or no question on the terminal. Anything other than `y` or `yes` is a no.
+pub fn confirm(question: &str) -> Result<bool, SheepError> {
+    print!("❓ {question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(is_yes(&answer))
+}
+
+/// Asks for a value on the terminal, returning `default` when nothing is entered.
+pub fn ask(question: &str, default: &str) -> Result<String, SheepError> {
+    print!("❓ {question} [{default}] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer_or_default(&answer, default))
+}
+
+fn answer_or_default(answer: &str, default: &str) -> String {
+    match answer.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    }
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prompt::{answer_or_default, is_yes};
+
+    #[test]
+    fn answer_or_default_answers() {
+        assert_eq!("v{version}", answer_or_default(" v{version}\n", "{version}"));
+        assert_eq!("{version}", answer_or_default("\n", "{version}"));
+    }
+
+    #[test]
+    fn is_yes_answers() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES "));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("no"));
+    }
+}
@@ -1,9 +1,13 @@
 pub mod branch;
 pub mod clone;
 pub mod commit;
+pub mod diff;
 pub mod path;
 mod reference;
 pub mod remote;
 pub mod open;
 mod ssh;
-pub mod tag;
\ No newline at end of file
+pub mod status;
+pub mod tag;
+#[cfg(test)]
+pub mod testing;
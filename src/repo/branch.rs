@@ -1,4 +1,4 @@
-use git2::{Error, Repository};
+use git2::{BranchType, Error, Oid, Repository};
 use crate::repo::commit;
 use crate::repo::reference::branch_ref_name;
 
@@ -23,4 +23,68 @@ impl GitBranches {
         repository.set_head(&ref_name)?;
         repository.checkout_head(None)
     }
+
+    /// Detaches HEAD at the provided commit and checks it out.
+    pub fn checkout_commit(&self, repository: &Repository, commit: Oid) -> Result<(), Error> {
+        repository.set_head_detached(commit)?;
+        repository.checkout_head(None)
+    }
+
+    pub fn has_branch(&self, repository: &Repository, branch_name: &str) -> bool {
+        repository.find_branch(branch_name, BranchType::Local).is_ok()
+    }
+
+    pub fn delete_branch(&self, repository: &Repository, branch_name: &str) -> Result<(), Error> {
+        repository.find_branch(branch_name, BranchType::Local)?.delete()
+    }
+
+    /// The remote's default branch if it is known, otherwise the branch HEAD points to, which may
+    /// not have any commits yet.
+    pub fn default_branch(&self, repository: &Repository, remote_name: &str) -> Option<String> {
+        let remote_head = format!("refs/remotes/{remote_name}/HEAD");
+        let remote_prefix = format!("refs/remotes/{remote_name}/");
+        let remote_branch = symbolic_target(repository, &remote_head)
+            .and_then(|target| target.strip_prefix(&remote_prefix).map(String::from));
+        remote_branch.or_else(|| {
+            symbolic_target(repository, "HEAD")
+                .and_then(|target| target.strip_prefix("refs/heads/").map(String::from))
+        })
+    }
+}
+
+fn symbolic_target(repository: &Repository, name: &str) -> Option<String> {
+    let reference = repository.find_reference(name).ok()?;
+    reference.symbolic_target().map(String::from)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::repo::branch::GitBranches;
+    use crate::repo::testing;
+
+    #[test]
+    fn delete_branch() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let branches = GitBranches::new();
+        testing::commit(&repo, "first");
+
+        branches.create_branch(&repo, "release/1.0.0").expect("failed to create branch");
+        assert!(branches.has_branch(&repo, "release/1.0.0"));
+        branches.delete_branch(&repo, "release/1.0.0").expect("failed to delete branch");
+        assert!(!branches.has_branch(&repo, "release/1.0.0"))
+    }
+
+    #[test]
+    fn default_branch() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let branches = GitBranches::new();
+        repo.set_head("refs/heads/trunk").unwrap();
+        assert_eq!(Some("trunk".to_string()), branches.default_branch(&repo, "origin"));
+
+        let commit = testing::commit(&repo, "first");
+        repo.reference("refs/remotes/origin/develop", commit, false, "fetch").unwrap();
+        let target = "refs/remotes/origin/develop";
+        repo.reference_symbolic("refs/remotes/origin/HEAD", target, false, "fetch").unwrap();
+        assert_eq!(Some("develop".to_string()), branches.default_branch(&repo, "origin"))
+    }
 }
\ No newline at end of file
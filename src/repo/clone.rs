@@ -1,18 +1,38 @@
 use std::path::Path;
 use git2;
-use git2::{Error, Repository};
+use git2::{BranchType, Error, Repository};
 use crate::repo::ssh;
 
-pub struct GitCloner {}
+pub struct GitCloner {
+    branch: Option<String>,
+    ssh_key_path: Option<String>,
+}
 
 impl GitCloner {
     pub fn new() -> Self {
-        return GitCloner {};
+        return GitCloner { branch: None, ssh_key_path: None };
+    }
+
+    /// Checks out `branch` after cloning instead of the remote's default branch. A tag or commit
+    /// which isn't a branch is checked out detached.
+    pub fn with_branch(self, branch: Option<&str>) -> Self {
+        GitCloner {
+            branch: branch.map(|branch| branch.to_string()),
+            ..self
+        }
+    }
+
+    /// Authenticates with the provided ssh key rather than the standard one.
+    pub fn with_ssh_key_path(self, ssh_key_path: Option<&str>) -> Self {
+        GitCloner {
+            ssh_key_path: ssh_key_path.map(|path| path.to_string()),
+            ..self
+        }
     }
 
     pub fn clone<P: AsRef<Path>>(&self, repo_url: &str, path: P) -> Result<Repository, Error> {
         let mut callbacks = git2::RemoteCallbacks::new();
-        ssh::add_credentials_to_callbacks(&mut callbacks);
+        ssh::add_credentials_to_callbacks(&mut callbacks, self.ssh_key_path.clone());
         // Prepare fetch options.
         let mut fetch_options = git2::FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
@@ -21,6 +41,73 @@ impl GitCloner {
         builder.fetch_options(fetch_options);
 
         // Clone the project.
-        builder.clone(repo_url, path.as_ref())
+        let repo = builder.clone(repo_url, path.as_ref())?;
+        if let Some(branch) = &self.branch {
+            checkout_ref(&repo, branch)?;
+        }
+        Ok(repo)
+    }
+}
+
+/// Checks out the remote branch named `name`, or else the tag or commit it names detached.
+fn checkout_ref(repo: &Repository, name: &str) -> Result<(), Error> {
+    if repo.head()?.shorthand() == Some(name) {
+        return Ok(());
+    }
+    let remote_branch = repo.find_branch(&format!("origin/{name}"), BranchType::Remote);
+    let commit = match &remote_branch {
+        Ok(remote_branch) => remote_branch.get().peel_to_commit()?,
+        Err(_) => repo.revparse_single(name)?.peel_to_commit()?,
+    };
+    repo.checkout_tree(commit.as_object(), None)?;
+    match remote_branch {
+        Ok(_) => {
+            let mut branch = repo.branch(name, &commit, false)?;
+            branch.set_upstream(Some(&format!("origin/{name}")))?;
+            repo.set_head(&format!("refs/heads/{name}"))
+        }
+        Err(_) => repo.set_head_detached(commit.id()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::repo::clone::GitCloner;
+    use crate::repo::testing;
+    use tempfile::TempDir;
+
+    #[test]
+    fn clone_branch() {
+        let (temp_dir, repo) = testing::init_repo();
+        let first = testing::commit(&repo, "first");
+        repo.branch("release/1.x", &repo.find_commit(first).unwrap(), false).unwrap();
+        testing::commit(&repo, "second");
+        let url = temp_dir.path().to_string_lossy().to_string();
+
+        let clone_dir = TempDir::new().unwrap();
+        let clone = GitCloner::new()
+            .with_branch(Some("release/1.x"))
+            .clone(&url, clone_dir.path())
+            .expect("failed to clone");
+        let head = clone.head().unwrap();
+        assert_eq!(Some("release/1.x"), head.shorthand());
+        assert_eq!(first, head.peel_to_commit().unwrap().id());
+    }
+
+    #[test]
+    fn clone_tag() {
+        let (temp_dir, repo) = testing::init_repo();
+        let first = testing::commit(&repo, "first");
+        repo.tag_lightweight("1.0.0", &repo.find_object(first, None).unwrap(), false).unwrap();
+        testing::commit(&repo, "second");
+        let url = temp_dir.path().to_string_lossy().to_string();
+
+        let clone_dir = TempDir::new().unwrap();
+        let clone = GitCloner::new()
+            .with_branch(Some("1.0.0"))
+            .clone(&url, clone_dir.path())
+            .expect("failed to clone");
+        assert!(clone.head_detached().unwrap());
+        assert_eq!(first, clone.head().unwrap().peel_to_commit().unwrap().id());
     }
 }
\ No newline at end of file
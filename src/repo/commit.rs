@@ -1,8 +1,12 @@
-use git2::{Commit, Config, Error, ObjectType, Oid, Repository, Tree};
+use std::path::PathBuf;
+use git2::build::CheckoutBuilder;
+use git2::{Commit, Config, Error, ObjectType, Oid, Repository, ResetType, Tree};
 use git2_ext::ops::{Sign, UserSign};
 
 pub struct GitCommits {
     default_branch: String,
+    paths: Vec<PathBuf>,
+    sign: bool,
 }
 
 pub fn find_last_commit(repo: &Repository) -> Result<Commit, Error> {
@@ -11,10 +15,48 @@ pub fn find_last_commit(repo: &Repository) -> Result<Commit, Error> {
         .map_err(|_| Error::from_str("Couldn't find commit"))
 }
 
+/// Moves the current branch back to the parent of the commit at HEAD and restores the committed
+/// paths to their contents in the parent. Other changes in the working directory are left alone.
+pub fn undo_commit(repo: &Repository, commit_id: Oid, paths: &[PathBuf]) -> Result<(), Error> {
+    let head_commit = find_last_commit(repo)?;
+    if head_commit.id() != commit_id {
+        let message = format!("HEAD has moved on from commit {commit_id}");
+        return Err(Error::from_str(&message));
+    }
+    let parent = head_commit.parent(0)?;
+    repo.reset(parent.as_object(), ResetType::Mixed, None)?;
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force();
+    for path in paths {
+        checkout.path(path);
+    }
+    repo.checkout_tree(parent.as_object(), Some(&mut checkout))
+}
+
 impl GitCommits {
     pub fn with_default_branch(default_branch: &str) -> GitCommits {
         GitCommits {
             default_branch: String::from(default_branch),
+            paths: vec![],
+            sign: true,
+        }
+    }
+
+    /// Controls whether commits are signed when git is configured to sign them.
+    pub fn with_signing(self, sign: bool) -> GitCommits {
+        GitCommits { sign, ..self }
+    }
+
+    /// Stages the provided paths, relative to the repository root, before committing. Paths
+    /// which no longer exist are removed from the index. The index is reset to HEAD first, so
+    /// only these paths are committed and anything else which was staged is unstaged.
+    pub fn with_paths(self, paths: &[PathBuf]) -> GitCommits {
+        GitCommits {
+            paths: paths.to_vec(),
+            ..self
         }
     }
 
@@ -23,7 +65,7 @@ impl GitCommits {
         let git_config = Config::open_default()?;
         let signature = repository.signature()?;
         // Get signing options
-        let user_sign = UserSign::from_config(repository, &git_config).ok();
+        let user_sign = self.user_sign(repository, &git_config);
         let signing = user_sign.as_ref().map(|sign| sign as &dyn Sign);
         // Add paths to index and turn it into a git tree.
         let tree = self.add_paths(repository)?;
@@ -45,9 +87,56 @@ impl GitCommits {
         })
     }
 
-    fn add_paths<'a>(&'a self, repository: &'a Repository) -> Result<Tree, Error> {
+    /// Commits a revert of the provided commit on top of HEAD, updating the working directory.
+    /// Fails without changing anything if the revert conflicts with later changes.
+    pub fn revert(&self, repository: &Repository, commit_id: Oid) -> Result<Oid, Error> {
+        let git_config = Config::open_default()?;
+        let signature = repository.signature()?;
+        let user_sign = self.user_sign(repository, &git_config);
+        let signing = user_sign.as_ref().map(|sign| sign as &dyn Sign);
+        // Revert in memory first so a conflict leaves the working directory untouched
+        let commit = repository.find_commit(commit_id)?;
+        let head_commit = find_last_commit(repository)?;
+        let mut index = repository.revert_commit(&commit, &head_commit, 0, None)?;
+        if index.has_conflicts() {
+            return Err(Error::from_str("the revert conflicts with later changes"));
+        }
+        let tree_id = index.write_tree_to(repository)?;
+        let tree = repository.find_tree(tree_id)?;
+        repository.checkout_tree(tree.as_object(), None)?;
+
+        let message = format!("Revert \"{}\"", commit.summary().unwrap_or_default());
+        git2_ext::ops::commit(
+            repository,
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &[&head_commit],
+            signing,
+        )
+        .and_then(|commit_id| self.update_head(repository, commit_id, &message))
+    }
+
+    fn user_sign(&self, repository: &Repository, git_config: &Config) -> Option<UserSign> {
+        if !self.sign {
+            return None;
+        }
+        UserSign::from_config(repository, git_config).ok()
+    }
+
+    fn add_paths<'a>(&'a self, repository: &'a Repository) -> Result<Tree<'a>, Error> {
         let mut index = repository.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.read_tree(&find_last_commit(repository)?.tree()?)?;
+        let workdir = repository.workdir()
+            .ok_or_else(|| Error::from_str("repository has no working directory"))?;
+        for path in &self.paths {
+            if workdir.join(path).exists() {
+                index.add_path(path)?;
+            } else {
+                index.remove_path(path)?;
+            }
+        }
         index.write()?;
         let oid = index.write_tree()?;
         repository.find_tree(oid)
@@ -68,11 +157,90 @@ impl GitCommits {
 
 #[cfg(test)]
 mod test {
-    use crate::repo::commit::GitCommits;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::repo::commit::{find_last_commit, undo_commit, GitCommits};
+    use crate::repo::testing;
 
     #[test]
     fn git_committer_with_default_branch() {
         let committer = GitCommits::with_default_branch("develop");
-        assert_eq!("develop", committer.default_branch)
+        assert_eq!("develop", committer.default_branch);
+        assert!(committer.paths.is_empty());
+        assert!(committer.sign)
+    }
+
+    #[test]
+    fn git_committer_with_signing() {
+        let committer = GitCommits::with_default_branch("develop").with_signing(false);
+        assert!(!committer.sign)
+    }
+
+    #[test]
+    fn git_committer_with_paths() {
+        let paths = vec![PathBuf::from("packages/api/Cargo.toml")];
+        let committer = GitCommits::with_default_branch("develop").with_paths(&paths);
+        assert_eq!(paths, committer.paths)
+    }
+
+    #[test]
+    fn commit_leaves_out_staged_files() {
+        let (_temp_dir, repo) = testing::init_repo();
+        testing::commit(&repo, "first");
+        testing::write_file(&repo, "staged.txt", "staged");
+        testing::write_file(&repo, "version.txt", "1.1.0");
+
+        GitCommits::with_default_branch("main")
+            .with_signing(false)
+            .with_paths(&[PathBuf::from("version.txt")])
+            .commit(&repo, "preparing release 1.1.0")
+            .expect("failed to commit");
+
+        let tree = find_last_commit(&repo).unwrap().tree().unwrap();
+        assert!(tree.get_name("version.txt").is_some());
+        assert!(tree.get_name("staged.txt").is_none())
+    }
+
+    #[test]
+    fn undo_commit_restores_parent() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "version.txt", "1.0.0");
+        let first = testing::commit(&repo, "first");
+        testing::write_file(&repo, "version.txt", "1.1.0");
+        let second = testing::commit(&repo, "second");
+
+        undo_commit(&repo, second, &[PathBuf::from("version.txt")]).expect("failed to undo");
+
+        assert_eq!(first, find_last_commit(&repo).unwrap().id());
+        let text = fs::read_to_string(temp_dir.path().join("version.txt")).unwrap();
+        assert_eq!("1.0.0", text)
+    }
+
+    #[test]
+    fn undo_commit_head_moved() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let first = testing::commit(&repo, "first");
+        testing::commit(&repo, "second");
+
+        undo_commit(&repo, first, &[]).expect_err("HEAD is no longer at the commit");
+    }
+
+    #[test]
+    fn revert_restores_previous_contents() {
+        let (temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "version.txt", "1.0.0");
+        testing::commit(&repo, "first");
+        testing::write_file(&repo, "version.txt", "1.1.0");
+        let release = testing::commit(&repo, "preparing release 1.1.0");
+
+        let revert = GitCommits::with_default_branch("main")
+            .revert(&repo, release)
+            .expect("failed to revert");
+
+        let head_commit = find_last_commit(&repo).unwrap();
+        assert_eq!(revert, head_commit.id());
+        assert_eq!(Some("Revert \"preparing release 1.1.0\""), head_commit.message());
+        let text = fs::read_to_string(temp_dir.path().join("version.txt")).unwrap();
+        assert_eq!("1.0.0", text)
     }
 }
@@ -0,0 +1,69 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use git2::{DiffOptions, Error, Oid, Repository};
+use crate::repo::commit;
+
+pub struct GitDiffs;
+
+impl GitDiffs {
+    pub fn new() -> Self {
+        GitDiffs {}
+    }
+
+    /// Returns the paths under `pathspec` which differ between `from_commit` and HEAD.
+    pub fn changed_paths(&self,
+                         repository: &Repository,
+                         from_commit: Oid,
+                         pathspec: &str) -> Result<Vec<PathBuf>, Error> {
+        let from_tree = repository.find_commit(from_commit)?.tree()?;
+        let head_tree = commit::find_last_commit(repository)?.tree()?;
+
+        let mut options = DiffOptions::new();
+        options.pathspec(pathspec);
+        let diff = repository.diff_tree_to_tree(Some(&from_tree),
+                                                Some(&head_tree),
+                                                Some(&mut options))?;
+
+        let paths: BTreeSet<PathBuf> = diff.deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .map(Path::to_path_buf)
+            .collect();
+        Ok(paths.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use crate::repo::diff::GitDiffs;
+    use crate::repo::testing;
+
+    #[test]
+    fn changed_paths_within_pathspec() {
+        let (_temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "api/lib.rs", "1");
+        testing::write_file(&repo, "web/index.js", "1");
+        let first = testing::commit(&repo, "first");
+        testing::write_file(&repo, "api/lib.rs", "2");
+        testing::write_file(&repo, "web/index.js", "2");
+        testing::commit(&repo, "second");
+
+        let paths = GitDiffs::new().changed_paths(&repo, first, "api")
+            .expect("failed to diff");
+        assert_eq!(vec![PathBuf::from("api/lib.rs")], paths)
+    }
+
+    #[test]
+    fn changed_paths_no_changes() {
+        let (_temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "api/lib.rs", "1");
+        let first = testing::commit(&repo, "first");
+        testing::write_file(&repo, "web/index.js", "1");
+        testing::commit(&repo, "second");
+
+        let paths = GitDiffs::new().changed_paths(&repo, first, "api")
+            .expect("failed to diff");
+        assert!(paths.is_empty())
+    }
+}
@@ -12,10 +12,22 @@ pub fn repo_path<P: AsRef<Path>>(repo_url: &str, directory: P) -> Result<PathBuf
     Ok(path_parts.iter().collect::<PathBuf>())
 }
 
-fn repo_name(repo_url: &str) -> Result<String, SheepError> {
+/// Reduces a git url to its host and full name so the same repository matches regardless of the
+/// url's scheme, user or `.git` suffix. Urls which can't be parsed are returned as is.
+pub fn normalized_url(repo_url: &str) -> String {
+    match GitUrl::parse(repo_url) {
+        Ok(git_url) if !git_url.fullname.is_empty() => {
+            let host = git_url.host.unwrap_or_default();
+            format!("{host}/{}", git_url.fullname).to_lowercase()
+        }
+        _ => repo_url.to_string(),
+    }
+}
+
+pub fn repo_name(repo_url: &str) -> Result<String, SheepError> {
     let git_url = GitUrl::parse(repo_url)?;
     if git_url.name.is_empty() {
-        Err(SheepError::new("no repo name found in git url"))
+        Err(SheepError::config("no repo name found in git url"))
     } else {
         Ok(git_url.name)
     }
@@ -48,4 +60,12 @@ mod test {
             .expect("failed to parse ssh url");
         assert_eq!(Path::new("/dir/sheepit"), path.as_path())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn normalized_url_matches_ssh_and_https() {
+        let ssh = path::normalized_url("git@github.com:Some-User/sdk.git");
+        let https = path::normalized_url("https://github.com/some-user/sdk");
+        assert_eq!("github.com/some-user/sdk", ssh);
+        assert_eq!(ssh, https)
+    }
+}
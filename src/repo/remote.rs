@@ -1,19 +1,32 @@
-use git2::{Direction, Error, PushOptions, RemoteCallbacks, Repository};
+use git2::{Direction, Error, PushOptions, Remote, RemoteCallbacks, Repository};
 use crate::error::SheepError;
 use crate::repo::{reference, ssh};
 
-pub struct GitRemotes;
+pub struct GitRemotes {
+    ssh_key_path: Option<String>,
+}
 
 impl GitRemotes {
     pub fn new() -> Self {
-        GitRemotes {}
+        GitRemotes { ssh_key_path: None }
+    }
+
+    /// Authenticates with the provided ssh key rather than the standard one.
+    pub fn with_ssh_key_path(self, ssh_key_path: Option<&str>) -> Self {
+        GitRemotes {
+            ssh_key_path: ssh_key_path.map(|path| path.to_string()),
+        }
+    }
+
+    pub fn has_remote(&self, repository: &Repository, remote_name: &str) -> bool {
+        repository.find_remote(remote_name).is_ok()
     }
 
     pub fn remote_url(&self,
                       repository: &Repository,
                       remote_name: &str) -> Result<String, SheepError> {
         let remote = repository.find_remote(remote_name)?;
-        let url = remote.url().ok_or(SheepError::new("no url from remote"))?;
+        let url = remote.url().ok_or(SheepError::git("no url from remote"))?;
         Ok(url.to_string())
     }
 
@@ -32,25 +45,115 @@ impl GitRemotes {
         self.push_ref(repository, &ref_name, remote_name)
     }
 
+    pub fn delete_branch(&self,
+                         repository: &Repository,
+                         branch_name: &str,
+                         remote_name: &str) -> Result<(), Error> {
+        let ref_name = reference::branch_ref_name(branch_name);
+        self.delete_ref(repository, &ref_name, remote_name)
+    }
+
+    pub fn delete_tag(&self, repository: &Repository,
+                      tag_name: &str,
+                      remote_name: &str) -> Result<(), Error> {
+        let ref_name = reference::tag_ref_name(tag_name);
+        self.delete_ref(repository, &ref_name, remote_name)
+    }
+
+    /// Deletes the ref from the remote. A ref the remote doesn't have is already deleted, so it
+    /// isn't an error.
+    fn delete_ref(&self,
+                  repository: &Repository,
+                  ref_name: &str,
+                  remote_name: &str) -> Result<(), Error> {
+        let mut remote = self.connect(repository, remote_name)?;
+        let mut rejection = None;
+        {
+            let mut push_callbacks = self.push_callbacks();
+            push_callbacks.push_update_reference(|_, status| {
+                rejection = status.filter(|status| !status.contains("does not exist"))
+                    .map(String::from);
+                Ok(())
+            });
+            let mut push_options = PushOptions::new();
+            push_options.remote_callbacks(push_callbacks);
+            remote.push(&[format!(":{ref_name}")], Some(&mut push_options))?;
+        }
+        match rejection {
+            Some(status) => Err(Error::from_str(&format!("failed to delete {ref_name}: {status}"))),
+            None => Ok(()),
+        }
+    }
+
     fn push_ref(&self, repository: &Repository,
                 ref_name: &str,
                 remote_name: &str) -> Result<(), Error> {
+        self.push_ref_spec(repository, &format!("{ref_name}:{ref_name}"), remote_name)
+    }
+
+    fn push_ref_spec(&self, repository: &Repository,
+                     ref_spec: &str,
+                     remote_name: &str) -> Result<(), Error> {
+        let mut remote = self.connect(repository, remote_name)?;
+        self.push(&mut remote, ref_spec)
+    }
+
+    fn connect<'a>(&self,
+                   repository: &'a Repository,
+                   remote_name: &str) -> Result<Remote<'a>, Error> {
         // Prepare credentials for remote connection.
         let mut callbacks = RemoteCallbacks::new();
-        ssh::add_credentials_to_callbacks(&mut callbacks);
+        ssh::add_credentials_to_callbacks(&mut callbacks, self.ssh_key_path.clone());
 
         // Connect to remote with authentication
         let mut remote = repository.find_remote(remote_name)?;
         remote.connect_auth(Direction::Push, Some(callbacks), None)?;
+        Ok(remote)
+    }
 
+    fn push(&self, remote: &mut Remote, ref_spec: &str) -> Result<(), Error> {
         // Prepare push options with authentication.
-        let mut push_callbacks = RemoteCallbacks::new();
-        ssh::add_credentials_to_callbacks(&mut push_callbacks);
         let mut push_options = PushOptions::new();
-        push_options.remote_callbacks(push_callbacks);
+        push_options.remote_callbacks(self.push_callbacks());
 
         // Push to remote
-        let ref_spec = format!("{ref_name}:{ref_name}");
         remote.push(&[ref_spec], Some(&mut push_options))
     }
-}
\ No newline at end of file
+
+    fn push_callbacks<'a>(&self) -> RemoteCallbacks<'a> {
+        let mut push_callbacks = RemoteCallbacks::new();
+        ssh::add_credentials_to_callbacks(&mut push_callbacks, self.ssh_key_path.clone());
+        push_callbacks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::repo::remote::GitRemotes;
+    use crate::repo::tag::GitTags;
+    use crate::repo::testing;
+    use git2::Repository;
+    use tempfile::TempDir;
+
+    #[test]
+    fn delete_tag() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let (_remote_dir, remote) = add_remote(&repo);
+        let remotes = GitRemotes::new();
+        testing::commit(&repo, "first");
+        GitTags::new().create_tag(&repo, "1.0.0", None).unwrap();
+        remotes.push_tag(&repo, "1.0.0", "origin").unwrap();
+        assert!(remote.find_reference("refs/tags/1.0.0").is_ok());
+
+        remotes.delete_tag(&repo, "1.0.0", "origin").expect("failed to delete tag");
+        assert!(remote.find_reference("refs/tags/1.0.0").is_err());
+        remotes.delete_tag(&repo, "1.0.0", "origin").expect("missing tag should be deleted");
+    }
+
+    fn add_remote(repo: &Repository) -> (TempDir, Repository) {
+        let remote_dir = TempDir::new().unwrap();
+        let remote = Repository::init_bare(remote_dir.path()).unwrap();
+        repo.remote("origin", &remote_dir.path().to_string_lossy()).unwrap();
+        (remote_dir, remote)
+    }
+}
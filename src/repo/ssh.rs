@@ -3,9 +3,12 @@ use git2::{Cred, Error};
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
-/// Returns the standard path to the user's ssh key.
-fn ssh_key_path() -> String {
-    path_from_env().unwrap_or_else(|| find_best_ssh_key().expect("failed to find ssh key"))
+/// Returns the path to the user's ssh key. `SHEEPIT_SSH_KEY_PATH` takes precedence over the
+/// configured path, and the standard key in `~/.ssh` is used when neither is set.
+fn ssh_key_path(configured_path: Option<&str>) -> String {
+    path_from_env()
+        .or_else(|| configured_path.map(|path| shellexpand::tilde(path).to_string()))
+        .unwrap_or_else(|| find_best_ssh_key().expect("failed to find ssh key"))
 }
 
 fn path_from_env() -> Option<String> {
@@ -37,16 +40,18 @@ fn find_best_key_name(file_names: Vec<String>) -> Result<String, SheepError> {
         .iter()
         .find(|name| !name.ends_with(".pub") && name.starts_with("id_"))
         .map(|name| name.to_string())
-        .ok_or(SheepError::new("failed to find ssh key"))
+        .ok_or(SheepError::auth("failed to find ssh key"))
 }
 
-pub fn add_credentials_to_callbacks(remote_callbacks: &mut git2::RemoteCallbacks) {
-    remote_callbacks
-        .credentials(|_, username_from_url, _| create_ssh_key(username_from_url.unwrap()));
+pub fn add_credentials_to_callbacks(remote_callbacks: &mut git2::RemoteCallbacks,
+                                    ssh_key_path: Option<String>) {
+    remote_callbacks.credentials(move |_, username_from_url, _| {
+        create_ssh_key(username_from_url.unwrap(), ssh_key_path.as_deref())
+    });
 }
 
-pub fn create_ssh_key(username_from_url: &str) -> Result<Cred, Error> {
-    let ssh_key_path = ssh_key_path();
+pub fn create_ssh_key(username_from_url: &str, configured_path: Option<&str>) -> Result<Cred, Error> {
+    let ssh_key_path = ssh_key_path(configured_path);
     Cred::ssh_key(username_from_url, None, Path::new(&ssh_key_path), None)
 }
 
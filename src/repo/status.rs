@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+use git2::{Error, Repository, StatusOptions};
+
+pub struct GitStatuses;
+
+impl GitStatuses {
+    pub fn new() -> Self {
+        GitStatuses {}
+    }
+
+    /// Returns the paths with uncommitted changes, including untracked files.
+    pub fn dirty_paths(&self, repository: &Repository) -> Result<Vec<PathBuf>, Error> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        let statuses = repository.statuses(Some(&mut options))?;
+        let paths = statuses.iter()
+            .filter_map(|entry| entry.path().map(PathBuf::from))
+            .collect();
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use crate::repo::status::GitStatuses;
+    use crate::repo::testing;
+
+    #[test]
+    fn dirty_paths_clean_repo() {
+        let (_temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "lib.rs", "1");
+        testing::commit(&repo, "first");
+
+        let paths = GitStatuses::new().dirty_paths(&repo).unwrap();
+        assert!(paths.is_empty())
+    }
+
+    #[test]
+    fn dirty_paths_with_changes() {
+        let (_temp_dir, repo) = testing::init_repo();
+        testing::write_file(&repo, "lib.rs", "1");
+        testing::commit(&repo, "first");
+        testing::write_file(&repo, "lib.rs", "2");
+
+        let paths = GitStatuses::new().dirty_paths(&repo).unwrap();
+        assert_eq!(vec![PathBuf::from("lib.rs")], paths)
+    }
+}
@@ -1,6 +1,7 @@
+use std::collections::HashSet;
 use git2::{Error, Oid, Repository};
 use git2::string_array::StringArray;
-use crate::repo::commit;
+use crate::repo::{commit, reference};
 
 pub struct GitTags;
 
@@ -30,6 +31,10 @@ impl GitTags {
         }
     }
 
+    pub fn delete_tag(&self, repository: &Repository, tag_name: &str) -> Result<(), Error> {
+        repository.tag_delete(tag_name)
+    }
+
     pub fn get_tags(&self, repository: &Repository) -> Result<Vec<String>, Error> {
         repository
             .tag_names(None)
@@ -38,9 +43,85 @@ impl GitTags {
             })
     }
 
+    /// Returns the tags whose target commit is reachable from HEAD.
+    pub fn get_reachable_tags(&self, repository: &Repository) -> Result<Vec<String>, Error> {
+        let mut revwalk = repository.revwalk()?;
+        revwalk.push_head()?;
+        let reachable = revwalk.collect::<Result<HashSet<Oid>, Error>>()?;
+
+        let tags = self.get_tags(repository)?;
+        let reachable_tags = tags.into_iter()
+            .filter(|tag_name| {
+                self.tag_commit(repository, tag_name)
+                    .map(|oid| reachable.contains(&oid))
+                    .unwrap_or(false)
+            })
+            .collect();
+        Ok(reachable_tags)
+    }
+
+    /// Returns the commit the tag points to.
+    pub fn tag_commit(&self, repository: &Repository, tag_name: &str) -> Result<Oid, Error> {
+        let ref_name = reference::tag_ref_name(tag_name);
+        let tag_ref = repository.find_reference(&ref_name)?;
+        Ok(tag_ref.peel_to_commit()?.id())
+    }
+
     fn map_tag_names_to_vec(&self, tag_names: &StringArray) -> Vec<String> {
         tag_names.iter().filter_map(|name| { name })
             .map(|name| String::from(name))
             .collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::repo::branch::GitBranches;
+    use crate::repo::tag::GitTags;
+    use crate::repo::testing;
+
+    #[test]
+    fn get_reachable_tags_skips_other_branches() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let tags = GitTags::new();
+        let branches = GitBranches::new();
+
+        testing::commit(&repo, "first");
+        tags.create_tag(&repo, "1.0.0", None).expect("failed to tag");
+        branches.create_branch(&repo, "maintenance").expect("failed to create branch");
+        testing::commit(&repo, "second");
+        tags.create_tag(&repo, "2.0.0", None).expect("failed to tag");
+        branches.checkout_branch(&repo, "maintenance").expect("failed to checkout");
+
+        let reachable = tags.get_reachable_tags(&repo).expect("failed to get tags");
+        assert_eq!(vec!["1.0.0".to_string()], reachable)
+    }
+
+    #[test]
+    fn delete_tag() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let tags = GitTags::new();
+
+        testing::commit(&repo, "first");
+        tags.create_tag(&repo, "1.0.0", None).expect("failed to tag");
+        tags.delete_tag(&repo, "1.0.0").expect("failed to delete tag");
+
+        let remaining = tags.get_tags(&repo).expect("failed to get tags");
+        assert!(remaining.is_empty())
+    }
+
+    #[test]
+    fn get_reachable_tags_includes_ancestors() {
+        let (_temp_dir, repo) = testing::init_repo();
+        let tags = GitTags::new();
+
+        testing::commit(&repo, "first");
+        tags.create_tag(&repo, "1.0.0", None).expect("failed to tag");
+        testing::commit(&repo, "second");
+        tags.create_tag(&repo, "1.1.0", None).expect("failed to tag");
+        testing::commit(&repo, "third");
+
+        let reachable = tags.get_reachable_tags(&repo).expect("failed to get tags");
+        assert_eq!(vec!["1.0.0".to_string(), "1.1.0".to_string()], reachable)
+    }
+}
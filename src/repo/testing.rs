@@ -0,0 +1,44 @@
+use git2::{Oid, Repository, Signature};
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Creates an empty repository in a temporary directory with a committer identity configured.
+pub fn init_repo() -> (TempDir, Repository) {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let repo = Repository::init(temp_dir.path()).expect("failed to init repo");
+    {
+        let mut config = repo.config().expect("failed to get config");
+        config.set_str("user.name", "sheep").expect("failed to set name");
+        config.set_str("user.email", "sheep@example.com").expect("failed to set email");
+    }
+    (temp_dir, repo)
+}
+
+/// Commits the current index on top of HEAD.
+pub fn commit(repo: &Repository, message: &str) -> Oid {
+    let signature = Signature::now("sheep", "sheep@example.com")
+        .expect("failed to create signature");
+    let tree_id = repo.index()
+        .and_then(|mut index| index.write_tree())
+        .expect("failed to write tree");
+    let tree = repo.find_tree(tree_id).expect("failed to find tree");
+    let parent = repo.head().ok()
+        .and_then(|head| head.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .expect("failed to commit")
+}
+
+/// Writes a file relative to the repository's working directory and stages it.
+pub fn write_file(repo: &Repository, path: &str, text: &str) {
+    let workdir = repo.workdir().expect("repo should have a working directory");
+    let full_path = workdir.join(path);
+    if let Some(parent) = full_path.parent() {
+        std::fs::create_dir_all(parent).expect("failed to create directories");
+    }
+    std::fs::write(&full_path, text).expect("failed to write file");
+
+    let mut index = repo.index().expect("failed to get index");
+    index.add_path(Path::new(path)).expect("failed to add path");
+    index.write().expect("failed to write index");
+}
@@ -1,66 +1,349 @@
+use crate::config::{Script, ScriptCommand};
+use crate::token;
 use crate::version::update::VersionUpdate;
 use crate::SheepError;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of output lines kept from each stream and included in the error for a failed script.
+const TAIL_LINES: usize = 20;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const DEFAULT_SHELL: &str = "sh";
 
 pub struct ScriptRunner {
     working_dir: PathBuf,
     version_update: VersionUpdate,
+    env: BTreeMap<String, String>,
+    repo_root: PathBuf,
+    shell: Option<String>,
 }
 
 impl ScriptRunner {
     pub fn new(working_dir: PathBuf, version_update: VersionUpdate) -> Self {
         Self {
+            repo_root: working_dir.clone(),
             working_dir,
             version_update,
+            env: BTreeMap::new(),
+            shell: None,
         }
     }
 
-    pub fn run(&self, script: Option<String>, script_name: &str) -> Result<(), SheepError> {
-        if script.is_none() {
+    /// The directory a script's `working_dir` is relative to, the working directory by default.
+    pub fn with_repo_root(self, repo_root: PathBuf) -> Self {
+        Self { repo_root, ..self }
+    }
+
+    /// The shell used for scripts which don't choose their own, `sh` by default.
+    pub fn with_shell(self, shell: Option<String>) -> Self {
+        Self { shell, ..self }
+    }
+
+    /// Adds an environment variable passed to every script, along with the version variables.
+    pub fn with_env<V: ToString>(mut self, name: &str, value: V) -> Self {
+        self.env.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn version_update(&self) -> &VersionUpdate {
+        &self.version_update
+    }
+
+    pub fn run(&self, script: Option<&Script>, script_name: &str) -> Result<(), SheepError> {
+        let Some(script) = script else {
             // Skip it empty
             return Ok(());
-        }
+        };
 
         println!("📜 Running script {script_name}");
 
-        let mut command = std::process::Command::new("sh");
+        let details = script.details();
+        let mut command = self.command(&details.command, details.shell.as_deref())?;
+        let working_dir = match &details.working_dir {
+            Some(working_dir) => self.repo_root.join(working_dir),
+            None => self.working_dir.clone(),
+        };
         command
-            .arg("-c")
-            .arg(script.unwrap())
-            .current_dir(&self.working_dir);
+            .current_dir(working_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
 
         // Set environment variables
-        command.env(
-            "SHEEPIT_CURRENT_VERSION",
-            self.version_update.current_version.to_string(),
-        );
-        command.env(
-            "SHEEPIT_NEXT_VERSION",
-            self.version_update.next_version.to_string(),
-        );
+        let current_version = &self.version_update.current_version;
+        let next_version = &self.version_update.next_version;
+        command.env("SHEEPIT_CURRENT_VERSION", current_version.to_string());
+        command.env("SHEEPIT_NEXT_VERSION", next_version.to_string());
+        command.env("SHEEPIT_NEXT_MAJOR", next_version.major.to_string());
+        command.env("SHEEPIT_NEXT_MINOR", next_version.minor.to_string());
+        command.env("SHEEPIT_NEXT_PATCH", next_version.patch.to_string());
+        command.env("SHEEPIT_NEXT_PRERELEASE", next_version.pre.as_str());
+        command.envs(&self.env);
+        for (name, value) in &details.env {
+            command.env(name, self.expand_tokens(value));
+        }
 
-        let output = command.output()?;
+        let mut child = command.spawn()?;
+        let stdout = OutputTail::stream(child.stdout.take(), script_name);
+        let stderr = OutputTail::stream(child.stderr.take(), script_name);
+        let status = wait(&mut child, details.timeout())?;
+        // Processes started by a killed script may still hold its output open, so don't wait for it
+        let is_finished = status.is_some();
+        stdout.finish(is_finished);
+        let stderr_tail = stderr.finish(is_finished);
 
-        if !output.status.success() {
-            let error_message = format!("Failed to run script {}", script_name);
-            return Err(SheepError::new(&error_message));
+        let error_message = match status {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => match status.code() {
+                Some(code) => format!("script {script_name} failed with exit code {code}"),
+                None => format!("script {script_name} was terminated by a signal"),
+            },
+            None => {
+                let timeout = details.timeout().unwrap_or_default().as_secs();
+                format!("script {script_name} timed out after {timeout}s and was killed")
+            }
+        };
+        let error_message = if stderr_tail.is_empty() {
+            error_message
+        } else {
+            format!("{error_message}, stderr:\n{}", stderr_tail.join("\n"))
+        };
+        if details.allow_failure {
+            println!("⚠️  {error_message}, continuing since failure is allowed");
+            return Ok(());
         }
-        Ok(())
+        Err(SheepError::script(&error_message))
+    }
+}
+
+impl ScriptRunner {
+    /// Builds the command for a shell string or, without a shell, for an argv array.
+    fn command(&self, script: &ScriptCommand, shell: Option<&str>) -> Result<Command, SheepError> {
+        match script {
+            ScriptCommand::Shell(text) => {
+                let shell = shell.or(self.shell.as_deref()).unwrap_or(DEFAULT_SHELL);
+                let mut command = Command::new(shell);
+                command.arg(shell_flag(shell)).arg(self.expand_tokens(text));
+                Ok(command)
+            }
+            ScriptCommand::Args(args) => {
+                let (program, args) = args
+                    .split_first()
+                    .ok_or_else(|| SheepError::script("script command array is empty"))?;
+                let mut command = Command::new(self.expand_tokens(program));
+                command.args(args.iter().map(|arg| self.expand_tokens(arg)));
+                Ok(command)
+            }
+        }
+    }
+
+    /// Replaces `{version}` and `{previous_version}` in the script with the release's versions.
+    fn expand_tokens(&self, command: &str) -> String {
+        command
+            .replace(token::VERSION, &self.version_update.next_version.to_string())
+            .replace(
+                token::PREVIOUS_VERSION,
+                &self.version_update.current_version.to_string(),
+            )
+    }
+}
+
+/// The flag which makes a shell run the following argument as a command.
+fn shell_flag(shell: &str) -> &'static str {
+    let name = Path::new(shell)
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match name.as_str() {
+        "pwsh" | "powershell" => "-Command",
+        "cmd" => "/C",
+        _ => "-c",
+    }
+}
+
+/// Waits for the child to exit, killing it once the timeout has passed. Returns `None` when the
+/// child was killed.
+fn wait(child: &mut Child, timeout: Option<Duration>) -> Result<Option<ExitStatus>, SheepError> {
+    let Some(timeout) = timeout else {
+        return Ok(Some(child.wait()?));
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Prints a child's output as it arrives, prefixed with the script name, while keeping the last
+/// few lines.
+struct OutputTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    reader: Option<thread::JoinHandle<()>>,
+}
+
+impl OutputTail {
+    fn stream<R: Read + Send + 'static>(output: Option<R>, script_name: &str) -> OutputTail {
+        let lines = Arc::new(Mutex::new(VecDeque::new()));
+        let reader = output.map(|output| {
+            let lines = Arc::clone(&lines);
+            let prefix = script_name.to_string();
+            thread::spawn(move || {
+                for line in BufReader::new(output).lines().map_while(Result::ok) {
+                    println!("  [{prefix}] {line}");
+                    let mut lines = lines.lock().expect("output lock poisoned");
+                    if lines.len() == TAIL_LINES {
+                        lines.pop_front();
+                    }
+                    lines.push_back(line);
+                }
+            })
+        });
+        OutputTail { lines, reader }
+    }
+
+    /// Returns the last lines, first waiting for the output to close if `wait` is true.
+    fn finish(self, wait: bool) -> Vec<String> {
+        if let Some(reader) = self.reader.filter(|_| wait) {
+            // A panic while printing only loses output, the script's status is still reported
+            let _ = reader.join();
+        }
+        let lines = self.lines.lock().expect("output lock poisoned");
+        lines.iter().cloned().collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ScriptDetails;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
 
     #[test]
     fn test_run_script() {
         let working_dir = PathBuf::from("/tmp");
         let version_update = VersionUpdate::new("1.0.0", "1.0.1");
-        let script = Some("echo 'Hello, world!'".to_string());
+        let script = Script::from("echo 'Hello, world!'");
         let script_name = "test_script";
         let runner = ScriptRunner::new(working_dir, version_update);
-        let result = runner.run(script, script_name);
+        let result = runner.run(Some(&script), script_name);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_run_failing_script() {
+        let working_dir = PathBuf::from("/tmp");
+        let version_update = VersionUpdate::new("1.0.0", "1.0.1");
+        let script = Script::from("echo 'tests failed' >&2; exit 3");
+        let runner = ScriptRunner::new(working_dir, version_update);
+        let result = runner.run(Some(&script), "failing_script");
+        let expected = SheepError::script(
+            "script failing_script failed with exit code 3, stderr:\ntests failed",
+        );
+        assert_eq!(Err(expected), result);
+    }
+
+    #[test]
+    fn test_run_script_timeout() {
+        let working_dir = PathBuf::from("/tmp");
+        let version_update = VersionUpdate::new("1.0.0", "1.0.1");
+        let script = Script::Detailed(ScriptDetails {
+            timeout: Some(1),
+            ..ScriptDetails::new(ScriptCommand::Shell("sleep 10".to_string()))
+        });
+        let runner = ScriptRunner::new(working_dir, version_update);
+        let result = runner.run(Some(&script), "slow_script");
+        let expected = SheepError::script("script slow_script timed out after 1s and was killed");
+        assert_eq!(Err(expected), result);
+    }
+
+    #[test]
+    fn test_run_script_environment() {
+        let working_dir = PathBuf::from("/tmp");
+        let version_update = VersionUpdate::new("1.0.0", "1.2.3-beta.1");
+        let script = Script::from(
+            r#"test "$SHEEPIT_NEXT_MINOR-$SHEEPIT_NEXT_PRERELEASE-$SHEEPIT_TAG_NAME" = "2-beta.1-v1.2.3""#,
+        );
+        let runner = ScriptRunner::new(working_dir, version_update)
+            .with_env("SHEEPIT_TAG_NAME", "v1.2.3");
+        let result = runner.run(Some(&script), "env_script");
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_run_args_without_shell() {
+        let working_dir = PathBuf::from("/tmp");
+        let version_update = VersionUpdate::new("1.0.0", "1.0.1");
+        let args = ["test", "{version}", "=", "1.0.1"].map(String::from).to_vec();
+        let script = Script::Command(ScriptCommand::Args(args));
+        let runner = ScriptRunner::new(working_dir, version_update);
+        assert!(runner.run(Some(&script), "args_script").is_ok());
+    }
+
+    #[test]
+    fn test_run_script_options() {
+        let repo_root = TempDir::new().unwrap();
+        std::fs::create_dir(repo_root.path().join("scripts")).unwrap();
+        let version_update = VersionUpdate::new("1.0.0", "1.0.1");
+        let script = Script::Detailed(ScriptDetails {
+            env: BTreeMap::from([("IMAGE".to_string(), "app:{version}".to_string())]),
+            working_dir: Some("scripts".to_string()),
+            ..ScriptDetails::new(ScriptCommand::Shell(
+                r#"test "$IMAGE" = "app:1.0.1" && test "$(basename "$PWD")" = scripts"#.to_string(),
+            ))
+        });
+        let runner = ScriptRunner::new(PathBuf::from("/tmp"), version_update)
+            .with_repo_root(repo_root.path().to_path_buf());
+        assert!(runner.run(Some(&script), "options_script").is_ok());
+    }
+
+    #[test]
+    fn test_run_allowed_failure() {
+        let working_dir = PathBuf::from("/tmp");
+        let version_update = VersionUpdate::new("1.0.0", "1.0.1");
+        let script = Script::Detailed(ScriptDetails {
+            allow_failure: true,
+            ..ScriptDetails::new(ScriptCommand::Shell("exit 1".to_string()))
+        });
+        let runner = ScriptRunner::new(working_dir, version_update);
+        assert!(runner.run(Some(&script), "allowed_script").is_ok());
+    }
+
+    #[test]
+    fn test_shell_flag() {
+        assert_eq!("-c", shell_flag("bash"));
+        assert_eq!("-c", shell_flag("/bin/zsh"));
+        assert_eq!("-Command", shell_flag("pwsh"));
+        assert_eq!("/C", shell_flag("cmd.exe"));
+    }
+
+    #[test]
+    fn expand_tokens() {
+        let working_dir = PathBuf::from("/tmp");
+        let version_update = VersionUpdate::new("1.0.0", "1.0.1");
+        let runner = ScriptRunner::new(working_dir, version_update);
+        let command =
+            runner.expand_tokens("docker build -t app:{version} --label from={previous_version}");
+        assert_eq!("docker build -t app:1.0.1 --label from=1.0.0", command);
+    }
+
+    #[test]
+    fn output_tail_keeps_last_lines() {
+        let text = (1..=30).map(|i| i.to_string()).collect::<Vec<_>>().join("\n");
+        let tail = OutputTail::stream(Some(std::io::Cursor::new(text)), "test").finish(true);
+        let expected = (11..=30).map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(expected, tail);
+    }
 }
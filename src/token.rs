@@ -1,3 +1,4 @@
+pub const PREVIOUS_VERSION: &str = "{previous_version}";
 pub const VERSION: &str = "{version}";
 
 #[derive(Debug, PartialEq)]
@@ -21,9 +22,11 @@ impl<'a> TokenTrimmer<'a> {
         Some(split)
     }
 
-    pub fn trim_text<'b>(&self, text: &'b str) -> &'b str {
-        let without_prefix = text.strip_prefix(self.before_token).unwrap_or(text);
-        without_prefix.strip_suffix(self.after_token).unwrap_or(without_prefix)
+    /// Returns the text in the token's position, or `None` if `text` doesn't have both the
+    /// text before and after the token.
+    pub fn trim_text<'b>(&self, text: &'b str) -> Option<&'b str> {
+        text.strip_prefix(self.before_token)?
+            .strip_suffix(self.after_token)
     }
 }
 
@@ -81,7 +84,7 @@ mod test {
         let token_trimmer = TokenTrimmer::new("prefix_$token_suffix", "$token")
             .expect("trimmer should exist");
         let trimmed_text = token_trimmer.trim_text("prefix_1.0.0_suffix");
-        assert_eq!("1.0.0", trimmed_text)
+        assert_eq!(Some("1.0.0"), trimmed_text)
     }
 
     #[test]
@@ -89,7 +92,7 @@ mod test {
         let token_trimmer = TokenTrimmer::new("prefix_$token", "$token")
             .expect("trimmer should exist");
         let trimmed_text = token_trimmer.trim_text("prefix_1.0.0_suffix");
-        assert_eq!("1.0.0_suffix", trimmed_text)
+        assert_eq!(Some("1.0.0_suffix"), trimmed_text)
     }
 
     #[test]
@@ -97,7 +100,7 @@ mod test {
         let token_trimmer = TokenTrimmer::new("prefix_$token", "$token")
             .expect("trimmer should exist");
         let trimmed_text = token_trimmer.trim_text("prefix_1.0.0");
-        assert_eq!("1.0.0", trimmed_text)
+        assert_eq!(Some("1.0.0"), trimmed_text)
     }
 
     #[test]
@@ -105,7 +108,7 @@ mod test {
         let token_trimmer = TokenTrimmer::new("$token_suffix", "$token")
             .expect("trimmer should exist");
         let trimmed_text = token_trimmer.trim_text("prefix_1.0.0_suffix");
-        assert_eq!("prefix_1.0.0", trimmed_text)
+        assert_eq!(Some("prefix_1.0.0"), trimmed_text)
     }
 
     #[test]
@@ -113,6 +116,22 @@ mod test {
         let token_trimmer = TokenTrimmer::new("$token_suffix", "$token")
             .expect("trimmer should exist");
         let trimmed_text = token_trimmer.trim_text("1.0.0_suffix");
-        assert_eq!("1.0.0", trimmed_text)
+        assert_eq!(Some("1.0.0"), trimmed_text)
+    }
+
+    #[test]
+    fn trim_text_missing_prefix() {
+        let token_trimmer = TokenTrimmer::new("prefix_$token", "$token")
+            .expect("trimmer should exist");
+        let trimmed_text = token_trimmer.trim_text("other_1.0.0");
+        assert_eq!(None, trimmed_text)
+    }
+
+    #[test]
+    fn trim_text_missing_suffix() {
+        let token_trimmer = TokenTrimmer::new("$token_suffix", "$token")
+            .expect("trimmer should exist");
+        let trimmed_text = token_trimmer.trim_text("1.0.0_other");
+        assert_eq!(None, trimmed_text)
     }
 }
\ No newline at end of file
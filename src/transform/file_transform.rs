@@ -4,6 +4,7 @@ use crate::file::{FileReader, FileWriter};
 use crate::version::update::VersionUpdate;
 use crate::{token, SheepError};
 use mockall_double::double;
+use semver::Version;
 use std::path::{Path, PathBuf};
 
 pub struct FileTransformer<'a> {
@@ -41,6 +42,27 @@ impl<'a> FileTransformer<'a> {
         Ok(relative_path.clone())
     }
 
+    /// Checks the file can be read and contains the text the transform would replace.
+    pub fn check(&self, current_version: &Version) -> Result<(), SheepError> {
+        let relative_path = &self.config.path;
+        let file_text = self
+            .file_reader
+            .read_to_string(self.full_path(relative_path))
+            .map_err(|error| {
+                SheepError::validation(&format!("can't read {relative_path}")).with_source(error)
+            })?;
+        let version_update = VersionUpdate {
+            current_version: current_version.clone(),
+            next_version: current_version.clone(),
+        };
+        let find = self.find_string(&version_update);
+        if !file_text.contains(&find) {
+            let message = format!("{relative_path} doesn't contain {find}");
+            return Err(SheepError::validation(&message));
+        }
+        Ok(())
+    }
+
     fn full_path(&self, relative_path: &str) -> PathBuf {
         [self.project_path, &Path::new(relative_path)]
             .iter()
@@ -147,6 +169,39 @@ mod test {
         assert_eq!(PATH, path)
     }
 
+    #[test]
+    fn check_finds_current_version() {
+        let reader = mock_reader("version_1.0.0");
+        let writer = MockFileWriter::default();
+        let config = TransformConfig {
+            path: PATH.to_string(),
+            find: None,
+            replace: "version_{version}".to_string(),
+        };
+        let project_path = project_path();
+        let file_transformer = FileTransformer::new(&config, &reader, &writer, &project_path);
+        file_transformer
+            .check(&version_update().current_version)
+            .expect("check failed")
+    }
+
+    #[test]
+    fn check_find_missing() {
+        let reader = mock_reader("version_0.9.0");
+        let writer = MockFileWriter::default();
+        let config = TransformConfig {
+            path: PATH.to_string(),
+            find: None,
+            replace: "version_{version}".to_string(),
+        };
+        let project_path = project_path();
+        let file_transformer = FileTransformer::new(&config, &reader, &writer, &project_path);
+        let error = file_transformer
+            .check(&version_update().current_version)
+            .expect_err("check should fail");
+        assert_eq!("path doesn't contain version_1.0.0", error.message())
+    }
+
     fn project_path() -> PathBuf {
         PathBuf::from(PROJECT_PATH)
     }
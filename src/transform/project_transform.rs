@@ -5,6 +5,7 @@ use crate::transform::file_transform::FileTransformer;
 use crate::version::update::VersionUpdate;
 use crate::SheepError;
 use mockall_double::double;
+use semver::Version;
 use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
@@ -40,20 +41,32 @@ impl ProjectTransformer {
         configs: &Vec<TransformConfig>,
         version_update: &VersionUpdate,
     ) -> Result<Vec<String>, SheepError> {
-        let transformers: Vec<FileTransformer> = configs
-            .iter()
-            .map(|c| {
-                FileTransformer::new(c, &self.file_reader, &self.file_writer, &self.project_path)
-            })
-            .collect();
-
         let mut paths: BTreeSet<String> = BTreeSet::new();
-        for transformer in transformers {
-            let path = transformer.transform(version_update)?;
+        for config in configs {
+            let transformer =
+                FileTransformer::new(config, &self.file_reader, &self.file_writer, &self.project_path);
+            let path = transformer.transform(version_update).map_err(|error| {
+                let message = format!("failed to transform {}", config.path);
+                SheepError::transform(&message).with_source(error)
+            })?;
             paths.insert(path);
         }
         Ok(Vec::from_iter(paths))
     }
+
+    /// Checks each transform could be applied to a project at `current_version`, returning a
+    /// description of each problem found.
+    pub fn check(&self, configs: &[TransformConfig], current_version: &Version) -> Vec<String> {
+        configs
+            .iter()
+            .filter_map(|config| {
+                FileTransformer::new(config, &self.file_reader, &self.file_writer, &self.project_path)
+                    .check(current_version)
+                    .err()
+                    .map(|error| error.report())
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -79,9 +92,10 @@ mod test {
             MockFileWriter::default(),
             PathBuf::from(PROJECT_PATH),
         );
-        project_transformer
+        let error = project_transformer
             .transform(&configs(), &version_update())
             .expect_err("should have failed");
+        assert_eq!(8, error.exit_code())
     }
 
     #[test]
@@ -99,7 +113,7 @@ mod test {
     fn failed_reader() -> MockFileReader {
         let mut mock = MockFileReader::default();
         mock.expect_read_to_string()
-            .return_once(|_| Err(SheepError::new("transform fail")));
+            .return_once(|_| Err(SheepError::transform("transform fail")));
         mock
     }
 
@@ -1,3 +1,5 @@
 pub mod bump;
+pub mod constraint;
 pub mod list;
-pub mod update;
\ No newline at end of file
+pub mod tag_match;
+pub mod update;
@@ -0,0 +1,138 @@
+use crate::SheepError;
+use glob::Pattern;
+use semver::{Version, VersionReq};
+use std::collections::BTreeMap;
+
+/// A semver requirement which all versions released from a branch must satisfy.
+#[derive(Debug, PartialEq)]
+pub struct VersionConstraint {
+    pub branch: String,
+    pub requirement: VersionReq,
+}
+
+impl VersionConstraint {
+    /// Finds the constraint for `branch`. Exact branch names take precedence over globs, otherwise
+    /// the first matching glob (in key order) is used.
+    pub fn for_branch(
+        constraints: &BTreeMap<String, String>,
+        branch: &str,
+    ) -> Result<Option<VersionConstraint>, SheepError> {
+        let requirement = match constraints.get(branch) {
+            Some(requirement) => Some(requirement),
+            None => Self::find_glob_requirement(constraints, branch)?,
+        };
+        let constraint = match requirement {
+            None => None,
+            Some(requirement) => Some(VersionConstraint {
+                branch: branch.to_string(),
+                requirement: VersionReq::parse(requirement).map_err(|error| {
+                    let message = format!("invalid version constraint '{requirement}' for {branch}");
+                    SheepError::config(&message).with_source(error)
+                })?,
+            }),
+        };
+        Ok(constraint)
+    }
+
+    fn find_glob_requirement<'a>(
+        constraints: &'a BTreeMap<String, String>,
+        branch: &str,
+    ) -> Result<Option<&'a String>, SheepError> {
+        for (branch_glob, requirement) in constraints {
+            if Pattern::new(branch_glob)?.matches(branch) {
+                return Ok(Some(requirement));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn validate(&self, version: &Version) -> Result<(), SheepError> {
+        if self.requirement.matches(version) {
+            Ok(())
+        } else {
+            let message = format!(
+                "version {version} is not allowed on branch {} (requires {})",
+                self.branch, self.requirement
+            );
+            Err(SheepError::validation(&message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::version::constraint::VersionConstraint;
+    use semver::{Version, VersionReq};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn for_branch_no_constraints() {
+        let constraint = VersionConstraint::for_branch(&BTreeMap::new(), "main")
+            .expect("failed to find constraint");
+        assert_eq!(None, constraint)
+    }
+
+    #[test]
+    fn for_branch_exact_match_wins() {
+        let constraint = VersionConstraint::for_branch(&constraints(), "release/1.8")
+            .expect("failed to find constraint");
+        let expected = VersionConstraint {
+            branch: "release/1.8".to_string(),
+            requirement: VersionReq::parse("~1.8").unwrap(),
+        };
+        assert_eq!(Some(expected), constraint)
+    }
+
+    #[test]
+    fn for_branch_glob_match() {
+        let constraint = VersionConstraint::for_branch(&constraints(), "release/1.x")
+            .expect("failed to find constraint");
+        let expected = VersionConstraint {
+            branch: "release/1.x".to_string(),
+            requirement: VersionReq::parse("^1").unwrap(),
+        };
+        assert_eq!(Some(expected), constraint)
+    }
+
+    #[test]
+    fn for_branch_no_match() {
+        let constraint = VersionConstraint::for_branch(&constraints(), "main")
+            .expect("failed to find constraint");
+        assert_eq!(None, constraint)
+    }
+
+    #[test]
+    fn for_branch_invalid_requirement() {
+        let mut constraints = BTreeMap::new();
+        constraints.insert("main".to_string(), "not a requirement".to_string());
+        let error = VersionConstraint::for_branch(&constraints, "main")
+            .expect_err("should fail to parse requirement");
+        assert!(error.message().contains("'not a requirement' for main"));
+        assert_eq!(3, error.exit_code())
+    }
+
+    #[test]
+    fn validate_matching_version() {
+        let constraint = VersionConstraint {
+            branch: "release/1.8".to_string(),
+            requirement: VersionReq::parse("1.8.*").unwrap(),
+        };
+        constraint.validate(&Version::new(1, 8, 4)).expect("version should be valid")
+    }
+
+    #[test]
+    fn validate_non_matching_version() {
+        let constraint = VersionConstraint {
+            branch: "release/1.8".to_string(),
+            requirement: VersionReq::parse("1.8.*").unwrap(),
+        };
+        constraint.validate(&Version::new(1, 9, 0)).expect_err("version should be invalid");
+    }
+
+    fn constraints() -> BTreeMap<String, String> {
+        let mut constraints = BTreeMap::new();
+        constraints.insert("release/*".to_string(), "^1".to_string());
+        constraints.insert("release/1.8".to_string(), "~1.8".to_string());
+        constraints
+    }
+}
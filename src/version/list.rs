@@ -1,28 +1,28 @@
-use semver::Version;
-use crate::token::TokenTrimmer;
+use semver::{Version, VersionReq};
+use crate::version::tag_match::{TagMatch, TagMatcher};
 
 #[derive(Debug, PartialEq)]
 pub struct VersionList(Vec<Version>);
 
 impl VersionList {
+    /// Parses versions from the tags accepted by any of the matchers.
     pub fn from_tag_list(tag_names: &Vec<String>,
-                         token_trimmer: Option<TokenTrimmer>) -> VersionList {
-        let trimmed_tag_names = Self::trimmed_tag_names(tag_names, token_trimmer);
-        let mut versions: Vec<Version> = trimmed_tag_names.iter()
-            .filter_map(|tag| lenient_semver::parse(tag).ok())
+                         matchers: &[TagMatcher]) -> VersionList {
+        let mut versions: Vec<Version> = tag_names.iter()
+            .map(|tag| TagMatch::from_matchers(tag, matchers))
+            .filter_map(|tag_match| tag_match.version().cloned())
             .collect();
         versions.sort();
+        versions.dedup();
         VersionList(versions)
     }
 
-    fn trimmed_tag_names<'a>(tag_names: &'a Vec<String>,
-                             token_trimmer: Option<TokenTrimmer>) -> Vec<&'a str> {
-        match token_trimmer {
-            None => tag_names.iter().map(|tag| tag.as_str()).collect(),
-            Some(trimmer) => tag_names.iter()
-                .map(|name| trimmer.trim_text(name))
-                .collect()
-        }
+    /// Returns a list containing only the versions which satisfy `requirement`.
+    pub fn matching(self, requirement: &VersionReq) -> VersionList {
+        let versions = self.0.into_iter()
+            .filter(|version| requirement.matches(version))
+            .collect();
+        VersionList(versions)
     }
 
     pub fn latest_version(&self) -> Option<Version> {
@@ -32,18 +32,19 @@ impl VersionList {
 
 #[cfg(test)]
 mod test {
-    use crate::token::TokenTrimmer;
+    use semver::{Version, VersionReq};
     use crate::version::list::VersionList;
+    use crate::version::tag_match::TagMatcher;
 
     #[test]
     fn from_tag_list_empty() {
-        let version_list = VersionList::from_tag_list(&vec![], None);
+        let version_list = VersionList::from_tag_list(&vec![], &bare_matchers());
         let expected = VersionList(vec![]);
         assert_eq!(expected, version_list)
     }
 
     #[test]
-    fn from_tag_list_no_trimmer() {
+    fn from_tag_list_bare_pattern() {
         let tags = vec![
             "10.0.0",
             "2.0.0",
@@ -52,7 +53,7 @@ mod test {
             "0.0.1",
         ];
         let string_tags = tags_to_string(&tags);
-        let version_list = VersionList::from_tag_list(&string_tags, None);
+        let version_list = VersionList::from_tag_list(&string_tags, &bare_matchers());
         let expected_tags = vec![
             "0.0.1",
             "0.1.0",
@@ -67,7 +68,7 @@ mod test {
     }
 
     #[test]
-    fn from_tag_list_with_trimmer() {
+    fn from_tag_list_with_pattern() {
         let tags = vec![
             "release_10.0.0_xxx",
             "2.0.0",
@@ -76,12 +77,11 @@ mod test {
             "release_0.0.1_xxx",
         ];
         let string_tags = tags_to_string(&tags);
-        let token_trimmer = TokenTrimmer::new("release_$version_xxx", "$version");
-        let version_list = VersionList::from_tag_list(&string_tags, token_trimmer);
+        let matchers = vec![TagMatcher::new("release_{version}_xxx").unwrap()];
+        let version_list = VersionList::from_tag_list(&string_tags, &matchers);
         let expected_tags = vec![
             "0.0.1",
             "0.1.0",
-            "2.0.0",
             "10.0.0",
         ];
         let expected_versions = expected_tags.iter()
@@ -92,15 +92,15 @@ mod test {
     }
 
     #[test]
-    fn from_tag_list_with_trimmer_trims_v() {
+    fn from_tag_list_with_pattern_trims_v() {
         let tags = vec![
             "v0.0.1",
             "v10.0.0",
             "v2.0.0"
         ];
         let string_tags = tags_to_string(&tags);
-        let token_trimmer = TokenTrimmer::new("v$version", "$version");
-        let version_list = VersionList::from_tag_list(&string_tags, token_trimmer);
+        let matchers = vec![TagMatcher::new("v{version}").unwrap()];
+        let version_list = VersionList::from_tag_list(&string_tags, &matchers);
         let expected_tags = vec![
             "0.0.1",
             "2.0.0",
@@ -113,9 +113,51 @@ mod test {
         assert_eq!(expected, version_list)
     }
 
+    #[test]
+    fn from_tag_list_with_multiple_patterns() {
+        let tags = vec![
+            "1.0.0",
+            "1.1.0",
+            "v1.2.0",
+            "myapp-1.3.0",
+            "v1.3.0",
+        ];
+        let string_tags = tags_to_string(&tags);
+        let matchers = vec![
+            TagMatcher::new("v{version}").unwrap(),
+            TagMatcher::new("myapp-{version}").unwrap(),
+            TagMatcher::new("{version}").unwrap(),
+        ];
+        let version_list = VersionList::from_tag_list(&string_tags, &matchers);
+        let expected_tags = [
+            "1.0.0",
+            "1.1.0",
+            "1.2.0",
+            "1.3.0",
+        ];
+        let expected_versions = expected_tags.iter()
+            .filter_map(|tag| lenient_semver::parse(tag).ok())
+            .collect::<Vec<_>>();
+        let expected = VersionList(expected_versions);
+        assert_eq!(expected, version_list)
+    }
+
+    #[test]
+    fn from_tag_list_rejects_unrelated_tags() {
+        let tags = vec![
+            "1.0.0",
+            "2023.01-backup",
+            "other-9.0.0",
+        ];
+        let string_tags = tags_to_string(&tags);
+        let version_list = VersionList::from_tag_list(&string_tags, &bare_matchers());
+        let expected = VersionList(vec![Version::new(1, 0, 0)]);
+        assert_eq!(expected, version_list)
+    }
+
     #[test]
     fn latest_version_empty_version_list() {
-        let version_list = VersionList::from_tag_list(&vec![], None);
+        let version_list = VersionList::from_tag_list(&vec![], &bare_matchers());
         assert_eq!(None, version_list.latest_version())
     }
 
@@ -127,7 +169,7 @@ mod test {
             "0.0.1",
         ];
         let string_tags = tags_to_string(&tags);
-        let version_list = VersionList::from_tag_list(&string_tags, None);
+        let version_list = VersionList::from_tag_list(&string_tags, &bare_matchers());
         let expected_version = lenient_semver::parse("10.0.0")
             .expect("expected version should parse");
         assert_eq!(Some(expected_version), version_list.latest_version())
@@ -141,12 +183,32 @@ mod test {
             "v0.0.1",
         ];
         let string_tags = tags_to_string(&tags);
-        let version_list = VersionList::from_tag_list(&string_tags, None);
+        let version_list = VersionList::from_tag_list(&string_tags, &bare_matchers());
         let expected_version = lenient_semver::parse("v10.0.0")
             .expect("expected version should parse");
         assert_eq!(Some(expected_version), version_list.latest_version())
     }
 
+    #[test]
+    fn matching_filters_versions() {
+        let tags = vec![
+            "1.8.2",
+            "1.8.3",
+            "1.9.0",
+            "2.4.0",
+        ];
+        let string_tags = tags_to_string(&tags);
+        let requirement = VersionReq::parse("1.8.*").unwrap();
+        let version_list = VersionList::from_tag_list(&string_tags, &bare_matchers())
+            .matching(&requirement);
+        let expected = VersionList(vec![Version::new(1, 8, 2), Version::new(1, 8, 3)]);
+        assert_eq!(expected, version_list)
+    }
+
+    fn bare_matchers() -> Vec<TagMatcher> {
+        vec![TagMatcher::new("{version}").unwrap()]
+    }
+
     fn tags_to_string(tags: &Vec<&str>) -> Vec<String> {
         tags.iter()
             .map(|tag| tag.to_string())
@@ -0,0 +1,246 @@
+use crate::token::TokenTrimmer;
+use crate::{token, SheepError};
+use regex::Regex;
+use semver::Version;
+
+/// Prefix which marks a tag pattern as a regular expression rather than a `{version}` pattern.
+pub const REGEX_PREFIX: &str = "regex:";
+
+/// The result of matching a tag against the project's tag patterns.
+#[derive(Debug, PartialEq)]
+pub enum TagMatch {
+    Accepted { version: Version, pattern: String },
+    Rejected { reason: String },
+}
+
+impl TagMatch {
+    /// Matches the tag against each matcher in order, accepting the first valid version. When
+    /// no matcher accepts the tag the first rejection for a matching pattern is used.
+    pub fn from_matchers(tag_name: &str, matchers: &[TagMatcher]) -> TagMatch {
+        let mut rejection = None;
+        for matcher in matchers {
+            match matcher.match_tag(tag_name) {
+                Some(accepted @ TagMatch::Accepted { .. }) => return accepted,
+                Some(rejected) => {
+                    rejection.get_or_insert(rejected);
+                }
+                None => {}
+            }
+        }
+        rejection.unwrap_or_else(|| TagMatch::Rejected {
+            reason: "does not match any tag pattern".to_string(),
+        })
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        match self {
+            TagMatch::Accepted { version, .. } => Some(version),
+            TagMatch::Rejected { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TagMatcher {
+    /// A pattern such as `v{version}` where the tag must match the text around the token exactly.
+    Pattern(String),
+    /// A regular expression which must match the whole tag. The version is taken from the
+    /// `version` capture group, the first capture group, or the whole match.
+    Regex { pattern: String, regex: Regex },
+}
+
+impl TagMatcher {
+    pub fn new(pattern: &str) -> Result<TagMatcher, SheepError> {
+        let matcher = match pattern.strip_prefix(REGEX_PREFIX) {
+            Some(expression) => TagMatcher::Regex {
+                pattern: pattern.to_string(),
+                regex: Regex::new(expression)?,
+            },
+            None => TagMatcher::Pattern(pattern.to_string()),
+        };
+        Ok(matcher)
+    }
+
+    pub fn pattern(&self) -> &str {
+        match self {
+            TagMatcher::Pattern(pattern) => pattern,
+            TagMatcher::Regex { pattern, .. } => pattern,
+        }
+    }
+
+    /// Returns `None` if the tag doesn't match the pattern, otherwise whether the text in the
+    /// version position is a valid version.
+    pub fn match_tag(&self, tag_name: &str) -> Option<TagMatch> {
+        let version_text = self.version_text(tag_name)?;
+        let tag_match = match parse_version(version_text) {
+            Ok(version) => TagMatch::Accepted {
+                version,
+                pattern: self.pattern().to_string(),
+            },
+            Err(error) => TagMatch::Rejected {
+                reason: format!(
+                    "'{version_text}' is not a valid version for pattern {}: {error}",
+                    self.pattern()
+                ),
+            },
+        };
+        Some(tag_match)
+    }
+
+    fn version_text<'a>(&self, tag_name: &'a str) -> Option<&'a str> {
+        match self {
+            TagMatcher::Pattern(pattern) if pattern == token::VERSION => Some(tag_name),
+            TagMatcher::Pattern(pattern) => {
+                TokenTrimmer::new(pattern, token::VERSION)?.trim_text(tag_name)
+            }
+            TagMatcher::Regex { regex, .. } => {
+                let captures = regex.captures(tag_name)?;
+                let whole_match = captures.get(0)?;
+                if whole_match.start() != 0 || whole_match.end() != tag_name.len() {
+                    return None;
+                }
+                let version_match = captures
+                    .name("version")
+                    .or_else(|| captures.get(1))
+                    .unwrap_or(whole_match);
+                Some(version_match.as_str())
+            }
+        }
+    }
+}
+
+/// Major versions at or above this look like a year, as in `2024.5`, rather than a version.
+const MAX_MAJOR_VERSION: u64 = 1000;
+
+/// Parses a version leniently, so `v1.2` is `1.2.0`, but rejects anything which looks like a
+/// date such as `2023.01` or `2024.5` rather than a version: numbers with leading zeros, more
+/// than three numbers and year-sized major versions.
+fn parse_version(text: &str) -> Result<Version, String> {
+    let core = text.split(['-', '+']).next().unwrap_or(text);
+    let numbers: Vec<&str> = core.trim_start_matches(['v', 'V']).split('.').collect();
+    if numbers.len() > 3 {
+        return Err("versions have at most three numbers".to_string());
+    }
+    let is_number = |number: &&str| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit());
+    if !numbers.iter().all(is_number) {
+        return Err("each part of the version must be a number".to_string());
+    }
+    if numbers.iter().any(|number| number.len() > 1 && number.starts_with('0')) {
+        return Err("numbers can't have leading zeros".to_string());
+    }
+    let version = lenient_semver::parse(text).map_err(|error| error.to_string())?;
+    if version.major >= MAX_MAJOR_VERSION {
+        return Err(format!("major version {} looks like a year", version.major));
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::version::tag_match::{TagMatch, TagMatcher};
+    use semver::Version;
+
+    #[test]
+    fn match_tag_bare_pattern() {
+        let matcher = TagMatcher::new("{version}").unwrap();
+        let expected = TagMatch::Accepted {
+            version: Version::new(1, 2, 3),
+            pattern: "{version}".to_string(),
+        };
+        assert_eq!(Some(expected), matcher.match_tag("1.2.3"))
+    }
+
+    #[test]
+    fn match_tag_bare_pattern_with_v() {
+        let matcher = TagMatcher::new("{version}").unwrap();
+        let version = matcher.match_tag("v1.2.3").and_then(|m| m.version().cloned());
+        assert_eq!(Some(Version::new(1, 2, 3)), version)
+    }
+
+    #[test]
+    fn match_tag_short_version() {
+        let matcher = TagMatcher::new("v{version}").unwrap();
+        let version = matcher.match_tag("v1.2").and_then(|m| m.version().cloned());
+        assert_eq!(Some(Version::new(1, 2, 0)), version);
+        let version = matcher.match_tag("v1").and_then(|m| m.version().cloned());
+        assert_eq!(Some(Version::new(1, 0, 0)), version)
+    }
+
+    #[test]
+    fn match_tag_bare_pattern_rejects_invalid_version() {
+        let matcher = TagMatcher::new("{version}").unwrap();
+        let tag_match = matcher.match_tag("2023.01-backup").expect("pattern should match");
+        assert_eq!(None, tag_match.version())
+    }
+
+    #[test]
+    fn match_tag_bare_pattern_rejects_dates() {
+        let matcher = TagMatcher::new("{version}").unwrap();
+        for tag_name in ["2023.12-backup", "2024.5", "2024", "1.2.3.4", "1.x"] {
+            let tag_match = matcher.match_tag(tag_name).expect("pattern should match");
+            assert_eq!(None, tag_match.version(), "{tag_name} should be rejected")
+        }
+    }
+
+    #[test]
+    fn match_tag_pattern_with_prefix() {
+        let matcher = TagMatcher::new("api-{version}").unwrap();
+        let version = matcher.match_tag("api-1.4.0").and_then(|m| m.version().cloned());
+        assert_eq!(Some(Version::new(1, 4, 0)), version)
+    }
+
+    #[test]
+    fn match_tag_pattern_with_other_prefix() {
+        let matcher = TagMatcher::new("api-{version}").unwrap();
+        assert_eq!(None, matcher.match_tag("other-9.0.0"))
+    }
+
+    #[test]
+    fn match_tag_regex_named_group() {
+        let matcher = TagMatcher::new("regex:(api|core)-(?P<version>.+)").unwrap();
+        let version = matcher.match_tag("core-2.0.1").and_then(|m| m.version().cloned());
+        assert_eq!(Some(Version::new(2, 0, 1)), version)
+    }
+
+    #[test]
+    fn match_tag_regex_first_group() {
+        let matcher = TagMatcher::new(r"regex:release-(\d+\.\d+\.\d+)").unwrap();
+        let version = matcher.match_tag("release-3.1.4").and_then(|m| m.version().cloned());
+        assert_eq!(Some(Version::new(3, 1, 4)), version)
+    }
+
+    #[test]
+    fn match_tag_regex_requires_full_match() {
+        let matcher = TagMatcher::new(r"regex:release-(\d+\.\d+\.\d+)").unwrap();
+        assert_eq!(None, matcher.match_tag("release-3.1.4-backup"))
+    }
+
+    #[test]
+    fn new_invalid_regex() {
+        TagMatcher::new("regex:(").expect_err("regex should be invalid");
+    }
+
+    #[test]
+    fn from_matchers_no_match() {
+        let matchers = vec![TagMatcher::new("v{version}").unwrap()];
+        let tag_match = TagMatch::from_matchers("other-1.0.0", &matchers);
+        let expected = TagMatch::Rejected {
+            reason: "does not match any tag pattern".to_string(),
+        };
+        assert_eq!(expected, tag_match)
+    }
+
+    #[test]
+    fn from_matchers_uses_first_accepted() {
+        let matchers = vec![
+            TagMatcher::new("{version}").unwrap(),
+            TagMatcher::new("myapp-{version}").unwrap(),
+        ];
+        let tag_match = TagMatch::from_matchers("myapp-1.0.0", &matchers);
+        let expected = TagMatch::Accepted {
+            version: Version::new(1, 0, 0),
+            pattern: "myapp-{version}".to_string(),
+        };
+        assert_eq!(expected, tag_match)
+    }
+}